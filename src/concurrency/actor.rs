@@ -0,0 +1,269 @@
+use std::{
+    sync::{mpsc::{sync_channel, Receiver, SyncSender, TrySendError}, Arc, Mutex}, thread::JoinHandle
+};
+
+use super::{pending::{Pending, Responder}, trigger::Trigger};
+
+/// State owned by a long lived worker thread that answers messages.
+pub trait Actor: Send + 'static {
+    type Message: Send + 'static;
+    type Reply: Send + 'static;
+
+    /// Handles a message. `responder` is `Some` for [ActorHandle::ask] and `None` for [ActorHandle::tell].
+    /// 
+    /// Dropping the responder without responding disconnects the asker's [Pending].
+    fn handle(&mut self, message: Self::Message, responder: Option<Responder<Self::Reply>>);
+
+    /// Called on the actor's thread once it has stopped handling messages.
+    fn stopped(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ActorError<M> {
+    #[error("Mailbox is full.")]
+    Full(M),
+    #[error("Actor has stopped.")]
+    Stopped(M),
+}
+
+impl<M> ActorError<M> {
+    /// Returns the message that couldn't be delivered.
+    #[inline]
+    pub fn into_inner(self) -> M {
+        match self {
+            Self::Full(message) | Self::Stopped(message) => message,
+        }
+    }
+}
+
+enum Envelope<A: Actor> {
+    Ask(A::Message, Responder<A::Reply>),
+    Tell(A::Message),
+    // Wakes the actor so that it notices a shutdown.
+    Wake,
+}
+
+/// A handle for sending messages to an [Actor] running on its own thread.
+/// 
+/// Cloning the handle is cheap. The actor stops once [ActorHandle::shutdown] is called
+/// or every handle has been dropped.
+pub struct ActorHandle<A: Actor> {
+    mailbox: SyncSender<Envelope<A>>,
+    shutdown: Trigger,
+    thread: Arc<Mutex<Option<JoinHandle<A>>>>,
+}
+
+impl<A: Actor> Clone for ActorHandle<A> {
+    fn clone(&self) -> Self {
+        Self {
+            mailbox: self.mailbox.clone(),
+            shutdown: self.shutdown.clone(),
+            thread: self.thread.clone(),
+        }
+    }
+}
+
+impl<A: Actor> std::fmt::Debug for ActorHandle<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActorHandle")
+            .field("stopped", &self.is_stopped())
+            .finish()
+    }
+}
+
+impl<A: Actor> ActorHandle<A> {
+    /// Starts `actor` on a new thread with a mailbox that holds up to `capacity` messages.
+    /// 
+    /// Once the mailbox is full, [ActorHandle::ask] and [ActorHandle::tell] block until there is room.
+    #[must_use]
+    pub fn spawn(actor: A, capacity: usize) -> Self {
+        let (mailbox, receiver) = sync_channel(capacity);
+        let shutdown = Trigger::new();
+        let thread = {
+            let shutdown = shutdown.clone();
+            std::thread::spawn(move || Self::run(actor, receiver, shutdown))
+        };
+        Self {
+            mailbox,
+            shutdown,
+            thread: Arc::new(Mutex::new(Some(thread))),
+        }
+    }
+
+    fn run(mut actor: A, receiver: Receiver<Envelope<A>>, shutdown: Trigger) -> A {
+        // Messages still in the mailbox at shutdown are dropped, disconnecting their askers.
+        while let Ok(envelope) = receiver.recv() {
+            if shutdown.activated() {
+                break;
+            }
+            match envelope {
+                Envelope::Ask(message, responder) => actor.handle(message, Some(responder)),
+                Envelope::Tell(message) => actor.handle(message, None),
+                Envelope::Wake => (),
+            }
+        }
+        shutdown.activate();
+        actor.stopped();
+        actor
+    }
+
+    fn send(&self, envelope: Envelope<A>) -> Result<(), Envelope<A>> {
+        if self.shutdown.activated() {
+            return Err(envelope);
+        }
+        self.mailbox.send(envelope).map_err(|err| err.0)
+    }
+
+    fn try_send(&self, envelope: Envelope<A>) -> Result<(), TrySendError<Envelope<A>>> {
+        if self.shutdown.activated() {
+            return Err(TrySendError::Disconnected(envelope));
+        }
+        self.mailbox.try_send(envelope)
+    }
+
+    /// Sends `message` and returns a [Pending] for the reply, waiting for room in the mailbox if it is full.
+    pub fn ask(&self, message: A::Message) -> Result<Pending<A::Reply>, ActorError<A::Message>> {
+        let (pending, responder) = Pending::pair();
+        match self.send(Envelope::Ask(message, responder)) {
+            Ok(()) => Ok(pending),
+            Err(envelope) => Err(ActorError::Stopped(Self::unwrap_message(envelope))),
+        }
+    }
+
+    /// Sends `message` and returns a [Pending] for the reply, or [ActorError::Full] if the mailbox is full.
+    pub fn try_ask(&self, message: A::Message) -> Result<Pending<A::Reply>, ActorError<A::Message>> {
+        let (pending, responder) = Pending::pair();
+        match self.try_send(Envelope::Ask(message, responder)) {
+            Ok(()) => Ok(pending),
+            Err(TrySendError::Full(envelope)) => Err(ActorError::Full(Self::unwrap_message(envelope))),
+            Err(TrySendError::Disconnected(envelope)) => Err(ActorError::Stopped(Self::unwrap_message(envelope))),
+        }
+    }
+
+    /// Sends `message` without waiting for a reply, waiting for room in the mailbox if it is full.
+    pub fn tell(&self, message: A::Message) -> Result<(), ActorError<A::Message>> {
+        self.send(Envelope::Tell(message))
+            .map_err(|envelope| ActorError::Stopped(Self::unwrap_message(envelope)))
+    }
+
+    /// Sends `message` without waiting for a reply, or returns [ActorError::Full] if the mailbox is full.
+    pub fn try_tell(&self, message: A::Message) -> Result<(), ActorError<A::Message>> {
+        match self.try_send(Envelope::Tell(message)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(envelope)) => Err(ActorError::Full(Self::unwrap_message(envelope))),
+            Err(TrySendError::Disconnected(envelope)) => Err(ActorError::Stopped(Self::unwrap_message(envelope))),
+        }
+    }
+
+    fn unwrap_message(envelope: Envelope<A>) -> A::Message {
+        match envelope {
+            Envelope::Ask(message, _) | Envelope::Tell(message) => message,
+            Envelope::Wake => unreachable!("Wake is never returned to the caller."),
+        }
+    }
+
+    /// Stops the actor after the message it is currently handling. Messages still in the
+    /// mailbox are dropped.
+    /// 
+    /// Returns `true` if this call stopped the actor.
+    pub fn shutdown(&self) -> bool {
+        let stopped = self.shutdown.activate();
+        if stopped {
+            // If the mailbox is full, the actor isn't waiting and will see the trigger on its own.
+            let _ = self.mailbox.try_send(Envelope::Wake);
+        }
+        stopped
+    }
+
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.shutdown.activated()
+    }
+
+    /// Waits for the actor's thread to finish and returns the actor.
+    /// 
+    /// Returns `None` if another handle has already joined the thread. If the actor panicked,
+    /// the panic is resumed on this thread.
+    pub fn join(&self) -> Option<A> {
+        let thread = self.thread.lock().unwrap_or_else(|err| err.into_inner()).take()?;
+        match thread.join() {
+            Ok(actor) => Some(actor),
+            Err(payload) => std::panic::resume_unwind(payload),
+        }
+    }
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_actor<A: Actor>(actor: A, capacity: usize) -> ActorHandle<A> {
+    ActorHandle::spawn(actor, capacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::concurrency::pending::PendingError;
+
+    use super::*;
+
+    struct Counter {
+        total: u64,
+        stopped: bool,
+    }
+
+    #[derive(Debug)]
+    enum Message {
+        Add(u64),
+        Get,
+        Sleep(Duration),
+    }
+
+    impl Actor for Counter {
+        type Message = Message;
+        type Reply = u64;
+
+        fn handle(&mut self, message: Self::Message, responder: Option<Responder<Self::Reply>>) {
+            match message {
+                Message::Add(amount) => self.total += amount,
+                Message::Get => (),
+                Message::Sleep(duration) => std::thread::sleep(duration),
+            }
+            if let Some(responder) = responder {
+                responder.respond(self.total);
+            }
+        }
+
+        fn stopped(&mut self) {
+            self.stopped = true;
+        }
+    }
+
+    #[test]
+    fn actor_test() {
+        let actor = spawn_actor(Counter { total: 0, stopped: false }, 2);
+        for i in 1..=10 {
+            actor.tell(Message::Add(i)).unwrap();
+        }
+        assert_eq!(actor.ask(Message::Get).unwrap().recv(), Ok(55));
+        assert_eq!(actor.clone().ask(Message::Add(5)).unwrap().recv(), Ok(60));
+
+        // fill the mailbox while the actor is busy.
+        let busy = actor.ask(Message::Sleep(Duration::from_millis(200))).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        let queued = actor.try_ask(Message::Add(1)).unwrap();
+        actor.try_tell(Message::Add(1)).unwrap();
+        assert!(matches!(actor.try_tell(Message::Add(100)), Err(ActorError::Full(Message::Add(100)))));
+
+        assert!(actor.shutdown());
+        assert!(!actor.shutdown());
+        assert!(actor.is_stopped());
+        assert!(matches!(actor.ask(Message::Get), Err(ActorError::Stopped(Message::Get))));
+        assert_eq!(busy.recv(), Ok(60));
+        assert_eq!(queued.recv(), Err(PendingError::Disconnected));
+        let counter = actor.join().unwrap();
+        assert!(counter.stopped);
+        assert_eq!(counter.total, 60);
+        assert!(actor.join().is_none());
+    }
+}
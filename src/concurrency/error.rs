@@ -1,8 +1,14 @@
-
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Out of Memory")]
     OutOfMemory,
 }
 
+impl From<super::pending::OutOfMemoryError> for Error {
+    #[inline]
+    fn from(_: super::pending::OutOfMemoryError) -> Self {
+        Self::OutOfMemory
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
@@ -0,0 +1,436 @@
+// Latches and barriers for coordinating pipeline stages. Like `Trigger`/`TriggerRef`, each
+// comes as an owned, cloneable type and a borrowed `Copy` type over a plain atomic.
+
+use std::{
+    sync::{atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Arc}, time::{Duration, Instant}
+};
+
+use crate::time::Delay;
+
+use super::parking;
+
+#[inline]
+fn deadline_after(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+/// A gate that threads can wait on, which can be opened and closed again.
+#[derive(Debug, Clone, Default)]
+pub struct Latch {
+    latch: Arc<AtomicBool>,
+}
+
+impl Latch {
+    /// Creates a closed latch.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the latch, waking everything waiting on it. Returns `true` if it was closed.
+    #[inline]
+    pub fn open(&self) -> bool {
+        self.latch_ref().open()
+    }
+
+    /// Closes the latch. Returns `true` if it was open.
+    #[inline]
+    pub fn close(&self) -> bool {
+        self.latch_ref().close()
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.latch_ref().is_open()
+    }
+
+    /// Blocks the current thread until the latch is open.
+    #[inline]
+    pub fn wait(&self) {
+        self.latch_ref().wait();
+    }
+
+    /// Blocks the current thread until the latch is open or `timeout` has elapsed.
+    /// Returns `true` if the latch was opened.
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.latch_ref().wait_timeout(timeout)
+    }
+
+    /// Blocks the current thread until the latch is open or `deadline` is ready.
+    /// Returns `true` if the latch was opened.
+    #[inline]
+    pub fn wait_until(&self, deadline: Delay) -> bool {
+        self.latch_ref().wait_until(deadline)
+    }
+
+    #[inline]
+    pub fn latch_ref(&self) -> LatchRef<'_> {
+        LatchRef::new(&self.latch)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LatchRef<'a> {
+    latch_ref: &'a AtomicBool,
+}
+
+impl<'a> LatchRef<'a> {
+    #[inline]
+    pub fn new(latch_ref: &'a AtomicBool) -> Self {
+        Self { latch_ref }
+    }
+
+    #[inline]
+    pub fn open(self) -> bool {
+        let opened = !self.latch_ref.swap(true, Ordering::AcqRel);
+        if opened {
+            parking::notify_all(self.latch_ref);
+        }
+        opened
+    }
+
+    #[inline]
+    pub fn close(self) -> bool {
+        self.latch_ref.swap(false, Ordering::AcqRel)
+    }
+
+    #[inline]
+    pub fn is_open(self) -> bool {
+        self.latch_ref.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn wait(self) {
+        parking::wait_until(self.latch_ref, || self.is_open(), None);
+    }
+
+    #[inline]
+    pub fn wait_timeout(self, timeout: Duration) -> bool {
+        parking::wait_until(self.latch_ref, || self.is_open(), deadline_after(timeout))
+    }
+
+    #[inline]
+    pub fn wait_until(self, deadline: Delay) -> bool {
+        parking::wait_until(self.latch_ref, || self.is_open(), Some(deadline.deadline()))
+    }
+}
+
+/// A latch that opens after [CountdownLatch::count_down] has been called a set number of times.
+#[derive(Debug, Clone)]
+pub struct CountdownLatch {
+    count: Arc<AtomicUsize>,
+}
+
+impl CountdownLatch {
+    /// Creates a latch that opens after `count` calls to [CountdownLatch::count_down].
+    /// With a `count` of zero, the latch starts out open.
+    #[inline]
+    pub fn new(count: usize) -> Self {
+        Self { count: Arc::new(AtomicUsize::new(count)) }
+    }
+
+    /// Decrements the count. Returns `true` if this call opened the latch.
+    #[inline]
+    pub fn count_down(&self) -> bool {
+        self.countdown_ref().count_down()
+    }
+
+    /// The number of [CountdownLatch::count_down] calls remaining until the latch opens.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.countdown_ref().count()
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.countdown_ref().is_open()
+    }
+
+    /// Blocks the current thread until the latch is open.
+    #[inline]
+    pub fn wait(&self) {
+        self.countdown_ref().wait();
+    }
+
+    /// Blocks the current thread until the latch is open or `timeout` has elapsed.
+    /// Returns `true` if the latch was opened.
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.countdown_ref().wait_timeout(timeout)
+    }
+
+    /// Blocks the current thread until the latch is open or `deadline` is ready.
+    /// Returns `true` if the latch was opened.
+    #[inline]
+    pub fn wait_until(&self, deadline: Delay) -> bool {
+        self.countdown_ref().wait_until(deadline)
+    }
+
+    #[inline]
+    pub fn countdown_ref(&self) -> CountdownLatchRef<'_> {
+        CountdownLatchRef::new(&self.count)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CountdownLatchRef<'a> {
+    count_ref: &'a AtomicUsize,
+}
+
+impl<'a> CountdownLatchRef<'a> {
+    #[inline]
+    pub fn new(count_ref: &'a AtomicUsize) -> Self {
+        Self { count_ref }
+    }
+
+    #[inline]
+    pub fn count_down(self) -> bool {
+        let opened = self.count_ref
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| count.checked_sub(1))
+            .is_ok_and(|count| count == 1);
+        if opened {
+            parking::notify_all(self.count_ref);
+        }
+        opened
+    }
+
+    #[inline]
+    pub fn count(self) -> usize {
+        self.count_ref.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn is_open(self) -> bool {
+        self.count() == 0
+    }
+
+    #[inline]
+    pub fn wait(self) {
+        parking::wait_until(self.count_ref, || self.is_open(), None);
+    }
+
+    #[inline]
+    pub fn wait_timeout(self, timeout: Duration) -> bool {
+        parking::wait_until(self.count_ref, || self.is_open(), deadline_after(timeout))
+    }
+
+    #[inline]
+    pub fn wait_until(self, deadline: Delay) -> bool {
+        parking::wait_until(self.count_ref, || self.is_open(), Some(deadline.deadline()))
+    }
+}
+
+const ARRIVED_MASK: u64 = u32::MAX as u64;
+const GENERATION_SHIFT: u32 = 32;
+
+/// The shared state of a [Barrier], for use with [BarrierRef].
+#[derive(Debug)]
+pub struct BarrierState {
+    // generation in the high 32 bits, arrived count in the low 32 bits.
+    state: AtomicU64,
+    parties: u32,
+}
+
+impl BarrierState {
+    /// Creates the state for a barrier that releases every `parties` arrivals (at least one).
+    #[inline]
+    pub const fn new(parties: u32) -> Self {
+        Self {
+            state: AtomicU64::new(0),
+            parties: if parties == 0 { 1 } else { parties },
+        }
+    }
+}
+
+/// Returned from [Barrier::wait].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BarrierWaitResult {
+    /// The generation that this wait was part of.
+    pub generation: u32,
+    /// `true` for exactly one of the waiters in each generation, the one that arrived last.
+    pub is_leader: bool,
+}
+
+/// A reusable barrier that blocks until a set number of threads have arrived, then releases them all.
+/// Each time the barrier releases, its generation increases.
+#[derive(Debug, Clone)]
+pub struct Barrier {
+    state: Arc<BarrierState>,
+}
+
+impl Barrier {
+    /// Creates a barrier that releases every `parties` arrivals (at least one).
+    #[inline]
+    pub fn new(parties: u32) -> Self {
+        Self { state: Arc::new(BarrierState::new(parties)) }
+    }
+
+    /// Blocks the current thread until all parties have arrived.
+    #[inline]
+    pub fn wait(&self) -> BarrierWaitResult {
+        self.barrier_ref().wait()
+    }
+
+    /// The number of times the barrier has released.
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.barrier_ref().generation()
+    }
+
+    #[inline]
+    pub fn parties(&self) -> u32 {
+        self.state.parties
+    }
+
+    #[inline]
+    pub fn barrier_ref(&self) -> BarrierRef<'_> {
+        BarrierRef::new(&self.state)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BarrierRef<'a> {
+    state_ref: &'a BarrierState,
+}
+
+impl<'a> BarrierRef<'a> {
+    #[inline]
+    pub fn new(state_ref: &'a BarrierState) -> Self {
+        Self { state_ref }
+    }
+
+    pub fn wait(self) -> BarrierWaitResult {
+        let state = &self.state_ref.state;
+        let parties = self.state_ref.parties as u64;
+        let mut current = state.load(Ordering::Acquire);
+        loop {
+            let generation = (current >> GENERATION_SHIFT) as u32;
+            let last = (current & ARRIVED_MASK) + 1 == parties;
+            // The last arrival starts the next generation with nobody arrived, in the same step
+            // as arriving, so there is never a full count for anyone else to arrive on.
+            let next = if last {
+                (generation.wrapping_add(1) as u64) << GENERATION_SHIFT
+            } else {
+                current + 1
+            };
+            match state.compare_exchange_weak(current, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) if last => {
+                    parking::notify_all(state);
+                    return BarrierWaitResult { generation, is_leader: true };
+                }
+                Ok(_) => {
+                    parking::wait_until(state, || self.generation() != generation, None);
+                    return BarrierWaitResult { generation, is_leader: false };
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    #[inline]
+    pub fn generation(self) -> u32 {
+        (self.state_ref.state.load(Ordering::Acquire) >> GENERATION_SHIFT) as u32
+    }
+
+    #[inline]
+    pub fn parties(self) -> u32 {
+        self.state_ref.parties
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latch_test() {
+        let latch = Latch::new();
+        assert!(!latch.wait_timeout(Duration::from_millis(20)));
+        let waiter = {
+            let latch = latch.clone();
+            std::thread::spawn(move || latch.wait_timeout(Duration::from_secs(10)))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(latch.open());
+        assert!(!latch.open());
+        assert!(waiter.join().unwrap());
+        assert!(latch.wait_until(Delay::millis(0)));
+        assert!(latch.close());
+        assert!(!latch.is_open());
+        assert!(!latch.latch_ref().wait_timeout(Duration::from_millis(20)));
+
+        let flag = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| LatchRef::new(&flag).wait());
+            std::thread::sleep(Duration::from_millis(20));
+            LatchRef::new(&flag).open();
+        });
+    }
+
+    #[test]
+    fn countdown_latch_test() {
+        let latch = CountdownLatch::new(3);
+        let waiters = (0..2).map(|_| {
+            let latch = latch.clone();
+            std::thread::spawn(move || latch.wait())
+        }).collect::<Vec<_>>();
+        assert!(!latch.count_down());
+        assert!(!latch.count_down());
+        assert!(!latch.wait_timeout(Duration::from_millis(20)));
+        assert_eq!(latch.count(), 1);
+        assert!(latch.count_down());
+        assert!(!latch.count_down());
+        assert!(latch.is_open());
+        waiters.into_iter().for_each(|waiter| waiter.join().unwrap());
+        assert!(CountdownLatch::new(0).is_open());
+
+        let count = AtomicUsize::new(1);
+        let latch = CountdownLatchRef::new(&count);
+        assert!(latch.count_down());
+        assert!(latch.wait_until(Delay::millis(0)));
+    }
+
+    #[test]
+    fn barrier_test() {
+        const PARTIES: u32 = 4;
+        const ROUNDS: u32 = 10;
+        let barrier = Barrier::new(PARTIES);
+        let arrived = Arc::new(AtomicUsize::new(0));
+        let threads = (0..PARTIES).map(|_| {
+            let barrier = barrier.clone();
+            let arrived = arrived.clone();
+            std::thread::spawn(move || {
+                let mut leaders = 0;
+                for round in 0..ROUNDS {
+                    arrived.fetch_add(1, Ordering::AcqRel);
+                    let result = barrier.wait();
+                    assert_eq!(result.generation, round);
+                    // nobody is released until everyone has arrived for this round.
+                    assert!(arrived.load(Ordering::Acquire) >= ((round + 1) * PARTIES) as usize);
+                    leaders += result.is_leader as u32;
+                }
+                leaders
+            })
+        }).collect::<Vec<_>>();
+        let leaders = threads.into_iter().map(|thread| thread.join().unwrap()).sum::<u32>();
+        assert_eq!(leaders, ROUNDS);
+        assert_eq!(barrier.generation(), ROUNDS);
+
+        // more threads than parties: extra arrivals belong to the next generation.
+        const THREADS: u32 = 8;
+        const WAITS: u32 = 20_000;
+        let barrier = Barrier::new(1);
+        let leaders = (0..THREADS).map(|_| {
+            let barrier = barrier.clone();
+            std::thread::spawn(move || (0..WAITS).filter(|_| barrier.wait().is_leader).count() as u32)
+        }).collect::<Vec<_>>().into_iter().map(|thread| thread.join().unwrap()).sum::<u32>();
+        assert_eq!(leaders, THREADS * WAITS);
+        assert_eq!(barrier.generation(), THREADS * WAITS);
+
+        let state = BarrierState::new(1);
+        let barrier = BarrierRef::new(&state);
+        assert!(barrier.wait().is_leader);
+        assert_eq!(barrier.generation(), 1);
+    }
+}
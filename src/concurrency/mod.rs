@@ -1,3 +1,16 @@
+pub mod actor;
 pub mod error;
+pub mod latch;
+mod parking;
 pub mod pending;
+pub mod pending_set;
+pub mod pending_stream;
+pub mod retry;
+pub mod ring_channel;
+pub mod shared_pending;
+#[cfg(target_os = "linux")]
+pub mod signal;
+pub mod single_flight;
+pub mod task_graph;
+mod timer;
 pub mod trigger;
\ No newline at end of file
@@ -0,0 +1,71 @@
+// Parking for threads waiting on a plain atomic, such as a `TriggerRef`'s `AtomicBool`.
+// Waiters are grouped into buckets by the address they wait on, so the atomic itself
+// doesn't need to carry a mutex and condvar. Addresses that share a bucket only cause
+// spurious wakeups.
+
+use std::{
+    sync::{Condvar, Mutex, MutexGuard}, time::Instant
+};
+
+const BUCKET_COUNT: usize = 64;
+
+struct Bucket {
+    lock: Mutex<()>,
+    signal: Condvar,
+}
+
+impl Bucket {
+    const fn new() -> Self {
+        Self {
+            lock: Mutex::new(()),
+            signal: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+static BUCKETS: [Bucket; BUCKET_COUNT] = [const { Bucket::new() }; BUCKET_COUNT];
+
+fn bucket<T>(address: &T) -> &'static Bucket {
+    // Fibonacci hashing spreads out addresses that only differ in their low bits.
+    let hash = (address as *const T as usize as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    &BUCKETS[(hash >> 58) as usize % BUCKET_COUNT]
+}
+
+/// Blocks until `condition` returns `true` or `deadline` passes. Returns the last result of `condition`.
+/// 
+/// Whatever makes `condition` true must call [notify_all] with the same `address` afterwards.
+pub(crate) fn wait_until<T, F: FnMut() -> bool>(address: &T, mut condition: F, deadline: Option<Instant>) -> bool {
+    if condition() {
+        return true;
+    }
+    let bucket = bucket(address);
+    let mut guard = bucket.lock();
+    loop {
+        if condition() {
+            return true;
+        }
+        guard = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return false;
+                }
+                bucket.signal.wait_timeout(guard, deadline - now)
+                    .unwrap_or_else(|err| err.into_inner()).0
+            }
+            None => bucket.signal.wait(guard).unwrap_or_else(|err| err.into_inner()),
+        };
+    }
+}
+
+/// Wakes every thread waiting on `address`.
+pub(crate) fn notify_all<T>(address: &T) {
+    let bucket = bucket(address);
+    // Taking the lock orders this after any waiter that checked its condition before the change.
+    drop(bucket.lock());
+    bucket.signal.notify_all();
+}
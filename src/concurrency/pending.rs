@@ -29,33 +29,55 @@ use std::{
         alloc, dealloc, Layout
     }, cell::UnsafeCell, mem::{
         MaybeUninit,
-    }, ptr::NonNull, sync::atomic::{AtomicU8, Ordering}
+    }, ptr::NonNull, future::Future, pin::Pin, sync::{atomic::{AtomicBool, AtomicU8, Ordering}, Arc, Mutex}, task::{Context, Poll, Wake, Waker}, thread::Thread, time::{Duration, Instant}
 };
 
+use crate::time::{Delay, TimedResult, TimedTaskResult};
+
+use super::{error::{Error, Result}, timer, trigger::TriggerRef};
+
 const TAKEN: u8 = 0;
 const WAITING: u8 = 1;
 const ASSIGNING: u8 = 2;
 const READY: u8 = 4;
+// The `Responder` was dropped without responding.
+const DISCONNECTED: u8 = 8;
+// The receiver's deadline passed before the value arrived. A late value is discarded.
+const TIMED_OUT: u8 = 16;
+
+/// Chooses where [Pending::spawn_with] runs its worker.
+pub trait SpawnStrategy: crate::Sealed<Pending<()>> {
+    fn spawn<F: FnOnce() + Send + 'static>(f: F);
+}
 
-// pub trait SpawnStrategy: crate::Sealed<Pending<()>> {
-//     fn spawn<F: FnOnce() + Send + 'static>(f: F);
-// }
-
-// pub struct StdThread;
-// pub struct RayonThread;
-
-// impl crate::Sealed<Pending<()>> for StdThread {}
-// impl crate::Sealed<Pending<()>> for RayonThread {}
-// impl SpawnStrategy for StdThread {
-//     fn spawn<F: FnOnce() + Send + 'static>(f: F) {
-//         std::thread::spawn(f);
-//     }
-// }
-// impl SpawnStrategy for RayonThread {
-//     fn spawn<F: FnOnce() + Send + 'static>(f: F) {
-//         rayon::spawn(f);
-//     }
-// }
+/// Runs the worker on a dedicated OS thread.
+pub struct StdThread;
+/// Runs the worker on the global rayon pool (or the current pool if called from a rayon thread).
+pub struct RayonThread;
+
+impl crate::Sealed<Pending<()>> for StdThread {}
+impl crate::Sealed<Pending<()>> for RayonThread {}
+impl SpawnStrategy for StdThread {
+    fn spawn<F: FnOnce() + Send + 'static>(f: F) {
+        std::thread::spawn(f);
+    }
+}
+impl SpawnStrategy for RayonThread {
+    fn spawn<F: FnOnce() + Send + 'static>(f: F) {
+        rayon::spawn(f);
+    }
+}
+
+/// A user supplied executor for [Pending::spawn_on].
+pub trait Executor {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>);
+}
+
+impl Executor for rayon::ThreadPool {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        self.spawn(job);
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
 pub enum PendingError {
@@ -65,14 +87,27 @@ pub enum PendingError {
     Waiting,
     #[error("Assigning value.")]
     Assigning,
+    #[error("Timed out waiting for value.")]
+    TimedOut,
+    #[error("Responder dropped without responding.")]
+    Disconnected,
+    #[error("Cancelled.")]
+    Cancelled,
 }
 
 #[repr(C)]
 struct Inner<R> {
     // we only need AtomicU8 since there can only be one sender and one receiver.
     result: UnsafeCell<MaybeUninit<R>>,
+    // woken by the responder. Either a task polling the `Pending` or a thread parked in `Pending::recv*`.
+    waker: Mutex<Option<Waker>>,
+    // wakes the registered waker at the receiver's deadline. Cancelled once it is no longer needed.
+    deadline_timer: Mutex<Option<timer::TimerId>>,
     ref_count: AtomicU8,
     state: AtomicU8,
+    cancelled: AtomicBool,
+    // where the allocation is returned to once it is no longer in use.
+    pool: Option<Arc<SlotPool<R>>>,
 }
 
 impl<R> Inner<R> {
@@ -81,19 +116,99 @@ impl<R> Inner<R> {
     }
 
     fn alloc_new() -> NonNull<Inner<R>> {
+        match Self::try_alloc_new(None) {
+            Ok(raw) => raw,
+            Err(OutOfMemoryError) => ::std::alloc::handle_alloc_error(Self::layout()),
+        }
+    }
+
+    /// Allocates a new [Inner], reusing a free slot from `pool` if there is one.
+    fn try_alloc_new(pool: Option<Arc<SlotPool<R>>>) -> std::result::Result<NonNull<Inner<R>>, OutOfMemoryError> {
         unsafe {
-            let layout = Self::layout();
-            let ptr = alloc(layout) as *mut Self;
-            let Some(raw) = NonNull::new(ptr) else {
-                ::std::alloc::handle_alloc_error(Self::layout());
+            let raw = match pool.as_ref().and_then(|pool| pool.take()) {
+                Some(raw) => raw,
+                None => {
+                    let ptr = alloc(Self::layout()) as *mut Self;
+                    NonNull::new(ptr).ok_or(OutOfMemoryError)?
+                }
             };
             raw.write(Self {
                 // initial reference count of 2 because there is one sender and one receiver.
                 ref_count: AtomicU8::new(2),
                 state: AtomicU8::new(WAITING),
                 result: UnsafeCell::new(MaybeUninit::uninit()),
+                waker: Mutex::new(None),
+                deadline_timer: Mutex::new(None),
+                cancelled: AtomicBool::new(false),
+                pool,
             });
-            raw
+            Ok(raw)
+        }
+    }
+
+    #[inline]
+    fn cancel_token(&self) -> TriggerRef<'_> {
+        TriggerRef::new(&self.cancelled)
+    }
+
+    /// Wakes the registered waker, if any.
+    fn wake_receiver(&self) {
+        self.cancel_deadline_timer();
+        let waker = self.waker.lock().unwrap_or_else(|err| err.into_inner()).take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Registers `waker` to be woken by the responder.
+    /// Returns `false` if `waker` was already registered.
+    fn register_waker(&self, waker: &Waker) -> bool {
+        let mut slot = self.waker.lock().unwrap_or_else(|err| err.into_inner());
+        match slot.as_mut() {
+            Some(current) if current.will_wake(waker) => false,
+            _ => {
+                *slot = Some(waker.clone());
+                true
+            }
+        }
+    }
+
+    /// Registers `waker` to be woken by the responder, or once `deadline` is ready,
+    /// whichever comes first.
+    fn register_waker_until(&self, waker: &Waker, deadline: Option<Delay>) {
+        if self.register_waker(waker)
+        && let Some(deadline) = deadline
+        && !deadline.is_ready() {
+            self.arm_deadline_timer(deadline, waker.clone());
+        }
+    }
+
+    /// Wakes `waker` at `deadline`, replacing the previous deadline wakeup.
+    fn arm_deadline_timer(&self, deadline: Delay, waker: Waker) {
+        let timer = timer::wake_at(deadline.deadline(), waker);
+        let previous = self.deadline_timer.lock().unwrap_or_else(|err| err.into_inner()).replace(timer);
+        if let Some(previous) = previous {
+            timer::cancel(previous);
+        }
+    }
+
+    fn cancel_deadline_timer(&self) {
+        let timer = self.deadline_timer.lock().unwrap_or_else(|err| err.into_inner()).take();
+        if let Some(timer) = timer {
+            timer::cancel(timer);
+        }
+    }
+
+    fn unregister_waker(&self) {
+        self.waker.lock().unwrap_or_else(|err| err.into_inner()).take();
+    }
+
+    /// Gives up on the value if it hasn't started arriving. The worker is told through the
+    /// cancellation token so that it can stop early.
+    fn time_out(&self) {
+        if self.state.compare_exchange(WAITING, TIMED_OUT, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+            self.cancel_token().activate();
+            self.wake_receiver();
         }
     }
 
@@ -114,7 +229,7 @@ impl<R> Inner<R> {
             let inner_mut = raw.as_mut();
             let state = inner_mut.state.load(Ordering::Acquire);
             match state {
-                TAKEN | WAITING => (/* Do nothing, there is no value. */),
+                TAKEN | WAITING | DISCONNECTED | TIMED_OUT => (/* Do nothing, there is no value. */),
                 ASSIGNING => {
                     unreachable!("Invalid state on cleanup.");
                 }
@@ -123,33 +238,241 @@ impl<R> Inner<R> {
                 }
                 unknown => unreachable!("Unknown state: {unknown}"),
             }
-            dealloc(raw.as_ptr() as *mut _, Self::layout());
+            inner_mut.cancel_deadline_timer();
+            let pool = inner_mut.pool.take();
+            // `result` has been handled above, this drops the remaining fields.
+            raw.drop_in_place();
+            match pool {
+                Some(pool) => pool.recycle(raw),
+                None => dealloc(raw.as_ptr() as *mut _, Self::layout()),
+            }
         }
 
     }
 }
 
+/// Free list of uninitialized `Inner<R>` allocations.
+struct SlotPool<R> {
+    free: Mutex<Vec<NonNull<Inner<R>>>>,
+    max_free: usize,
+}
+
+// SAFETY: the pointers in the free list are uninitialized memory owned by the pool.
+unsafe impl<R> Send for SlotPool<R> {}
+unsafe impl<R> Sync for SlotPool<R> {}
+
+impl<R> SlotPool<R> {
+    fn take(&self) -> Option<NonNull<Inner<R>>> {
+        self.free.lock().unwrap_or_else(|err| err.into_inner()).pop()
+    }
+
+    /// Returns a slot to the pool, deallocating it if the pool is full.
+    /// 
+    /// `raw` must not be initialized.
+    unsafe fn recycle(&self, raw: NonNull<Inner<R>>) {
+        let mut free = self.free.lock().unwrap_or_else(|err| err.into_inner());
+        if free.len() < self.max_free {
+            free.push(raw);
+        } else {
+            drop(free);
+            unsafe {
+                dealloc(raw.as_ptr() as *mut _, Inner::<R>::layout());
+            }
+        }
+    }
+}
+
+impl<R> Drop for SlotPool<R> {
+    fn drop(&mut self) {
+        let free = self.free.get_mut().unwrap_or_else(|err| err.into_inner());
+        for raw in free.drain(..) {
+            unsafe {
+                dealloc(raw.as_ptr() as *mut _, Inner::<R>::layout());
+            }
+        }
+    }
+}
+
+/// Recycles the allocations behind [Pending]/[Responder] pairs so that creating
+/// many short lived pairs doesn't go through the global allocator every time.
+/// 
+/// Cloning the pool is cheap, clones share the same free list.
+pub struct PendingPool<R: Send + 'static> {
+    slots: Arc<SlotPool<R>>,
+}
+
+impl<R: Send + 'static> Clone for PendingPool<R> {
+    fn clone(&self) -> Self {
+        Self { slots: self.slots.clone() }
+    }
+}
+
+impl<R: Send + 'static> std::fmt::Debug for PendingPool<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingPool")
+            .field("free_slots", &self.free_slots())
+            .field("max_free", &self.slots.max_free)
+            .finish()
+    }
+}
+
+impl<R: Send + 'static> PendingPool<R> {
+    /// Creates a pool that keeps up to `max_free` unused allocations around for reuse.
+    #[must_use]
+    pub fn new(max_free: usize) -> Self {
+        Self {
+            slots: Arc::new(SlotPool {
+                free: Mutex::new(Vec::new()),
+                max_free,
+            }),
+        }
+    }
+
+    /// Allocates `count` slots up front (up to `max_free`).
+    pub fn try_reserve(&self, count: usize) -> Result<()> {
+        let mut free = self.slots.free.lock().unwrap_or_else(|err| err.into_inner());
+        let count = count.min(self.slots.max_free.saturating_sub(free.len()));
+        free.try_reserve(count).map_err(|_| Error::OutOfMemory)?;
+        for _ in 0..count {
+            let ptr = unsafe { alloc(Inner::<R>::layout()) } as *mut Inner<R>;
+            free.push(NonNull::new(ptr).ok_or(OutOfMemoryError)?);
+        }
+        Ok(())
+    }
+
+    /// The number of unused allocations currently held by the pool.
+    #[inline]
+    pub fn free_slots(&self) -> usize {
+        self.slots.free.lock().unwrap_or_else(|err| err.into_inner()).len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn pair(&self) -> (Pending<R>, Responder<R>) {
+        match self.try_pair() {
+            Ok(pair) => pair,
+            Err(_) => ::std::alloc::handle_alloc_error(Inner::<R>::layout()),
+        }
+    }
+
+    #[inline]
+    pub fn try_pair(&self) -> Result<(Pending<R>, Responder<R>)> {
+        let raw = Inner::<R>::try_alloc_new(Some(self.slots.clone()))?;
+        Ok((
+            Pending::from_raw(raw),
+            Responder::from_raw(raw),
+        ))
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn spawn<F: FnOnce() -> R + Send + 'static>(&self, worker: F) -> Pending<R> {
+        let (pending, responder) = self.pair();
+        rayon::spawn(move || {
+            responder.respond(worker());
+        });
+        pending
+    }
+
+    #[inline]
+    pub fn try_spawn<F: FnOnce() -> R + Send + 'static>(&self, worker: F) -> Result<Pending<R>> {
+        let (pending, responder) = self.try_pair()?;
+        rayon::spawn(move || {
+            responder.respond(worker());
+        });
+        Ok(pending)
+    }
+}
+
+/// Unparks a thread blocked in `Pending::recv*`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+type Callback<R> = Box<dyn FnOnce(std::result::Result<R, PendingError>) + Send + 'static>;
+
+/// Waker that runs a callback with the result once the `Pending` resolves.
+struct Continuation<R: Send + 'static> {
+    // taken by the wake that sees the `Pending` resolved.
+    slot: Mutex<Option<(Pending<R>, Callback<R>)>>,
+}
+
+impl<R: Send + 'static> Wake for Continuation<R> {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let (pending, callback) = {
+            let mut slot = self.slot.lock().unwrap_or_else(|err| err.into_inner());
+            match slot.as_ref() {
+                Some((pending, _)) if pending.is_resolved() => (),
+                _ => return,
+            }
+            let Some(taken) = slot.take() else {
+                unreachable!("Continuation slot emptied while locked.");
+            };
+            taken
+        };
+        // `try_recv` can time the `Pending` out, which wakes this continuation again,
+        // so it must not be called while the slot is locked.
+        let result = pending.try_recv();
+        drop(pending);
+        callback(result);
+    }
+}
+
+/// Lets the worker check whether the [Pending] has been cancelled. Only the [Pending] can cancel.
+#[derive(Debug, Clone, Copy)]
+pub struct CancelToken<'a> {
+    cancelled: &'a AtomicBool,
+}
+
+impl CancelToken<'_> {
+    #[inline]
+    pub fn is_cancelled(self) -> bool {
+        TriggerRef::new(self.cancelled).activated()
+    }
+
+    /// Blocks the current thread until the [Pending] is cancelled or `timeout` has elapsed.
+    /// Returns `true` if it was cancelled.
+    #[inline]
+    pub fn wait_timeout(self, timeout: Duration) -> bool {
+        TriggerRef::new(self.cancelled).wait_timeout(timeout)
+    }
+}
+
 #[derive(Debug)]
-pub struct Pending<R: Send + 'static> {
+pub struct Pending<R: Send> {
     raw: NonNull<Inner<R>>,
+    cancel_on_drop: bool,
+    deadline: Option<Delay>,
 }
 
 #[derive(Debug)]
-pub struct Responder<R: Send + 'static> {
+pub struct Responder<R: Send> {
     raw: NonNull<Inner<R>>,
 }
 
 unsafe impl<R> Send for Pending<R>
-where R: Send + 'static {}
+where R: Send {}
 unsafe impl<R> Sync for Pending<R>
-where R: Send + Sync + 'static {}
+where R: Send + Sync {}
 
 unsafe impl<R> Send for Responder<R>
-where R: Send + 'static {}
+where R: Send {}
 unsafe impl<R> Sync for Responder<R>
-where R: Send + Sync + 'static {}
+where R: Send + Sync {}
 
-impl<R: Send + 'static> Responder<R> {
+impl<R: Send> Responder<R> {
     #[must_use]
     #[inline]
     fn from_raw(raw: NonNull<Inner<R>>) -> Self {
@@ -162,23 +485,42 @@ impl<R: Send + 'static> Responder<R> {
         let inner_ref = unsafe {
             self.raw.as_ref()
         };
-        // We use store because this is the only thing that can modify the value.
-        // The responder is the writer, and the `Pending` is the reader.
-        inner_ref.state.store(ASSIGNING, Ordering::Release);
+        // The responder is the writer, and the `Pending` is the reader. The only other
+        // transition out of WAITING is the receiver timing out, in which case the late
+        // result is dropped here.
+        if inner_ref.state.compare_exchange(WAITING, ASSIGNING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return;
+        }
         // SAFETY: dst is guaranteed to be valid for writes, and is properly aligned.
         unsafe {
             inner_ref.result.get().write(MaybeUninit::new(result));
         }
         inner_ref.state.store(READY, Ordering::Release);
+        inner_ref.wake_receiver();
+    }
+
+    /// Returns `true` if the receiver has cancelled the work or timed out waiting for it.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token().is_cancelled()
+    }
+
+    /// Returns a read-only view of the cancellation flag shared with the [Pending].
+    #[inline]
+    pub fn cancel_token(&self) -> CancelToken<'_> {
+        // SAFETY: self.raw is guaranteed to be convertible to a reference.
+        CancelToken {
+            cancelled: &unsafe { self.raw.as_ref() }.cancelled,
+        }
     }
 }
 
-impl<R: Send + 'static> Pending<R> {
+impl<R: Send> Pending<R> {
 
     #[must_use]
     #[inline]
     fn from_raw(raw: NonNull<Inner<R>>) -> Self {
-        Self { raw }
+        Self { raw, cancel_on_drop: false, deadline: None }
     }
 
     #[must_use]
@@ -191,22 +533,213 @@ impl<R: Send + 'static> Pending<R> {
         )
     }
 
+    /// Like [Pending::pair], but returns [Error::OutOfMemory] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_pair() -> Result<(Self, Responder<R>)> {
+        let raw = Inner::<R>::try_alloc_new(None)?;
+        Ok((
+            Self::from_raw(raw),
+            Responder::from_raw(raw)
+        ))
+    }
+
+}
+
+// Spawning
+impl<R: Send + 'static> Pending<R> {
+    /// Like [Pending::spawn], but returns [Error::OutOfMemory] instead of aborting if allocation fails.
+    #[inline]
+    pub fn try_spawn<F: FnOnce() -> R + Send + 'static>(worker: F) -> Result<Self> {
+        let (pending, responder) = Self::try_pair()?;
+        rayon::spawn(move || {
+            responder.respond(worker());
+        });
+        Ok(pending)
+    }
+
     #[must_use]
     #[inline]
     pub fn spawn<F: FnOnce() -> R + Send + 'static>(worker: F) -> Self {
+        Self::spawn_with::<RayonThread, F>(worker)
+    }
+
+    /// Spawns `worker` using the [SpawnStrategy] `S`.
+    #[must_use]
+    #[inline]
+    pub fn spawn_with<S: SpawnStrategy, F: FnOnce() -> R + Send + 'static>(worker: F) -> Self {
+        let (pending, responder) = Self::pair();
+        S::spawn(#[inline(always)] move || {
+            responder.respond(worker());
+        });
+        pending
+    }
+
+    /// Spawns `worker` with a cancellation token that is activated by [Pending::cancel].
+    /// 
+    /// Once cancelled, the receiver gets [PendingError::Cancelled] and the result
+    /// of `worker` is discarded, so the worker should return early when it sees the token.
+    #[must_use]
+    #[inline]
+    pub fn spawn_cancellable<F: FnOnce(CancelToken<'_>) -> R + Send + 'static>(worker: F) -> Self {
+        let (pending, responder) = Self::pair();
+        rayon::spawn(move || {
+            let result = worker(responder.cancel_token());
+            responder.respond(result);
+        });
+        pending
+    }
+
+    /// Spawns `worker` and gives up on its result once `deadline` is ready. See [Pending::with_deadline].
+    #[must_use]
+    #[inline]
+    pub fn spawn_with_deadline<F: FnOnce() -> R + Send + 'static>(deadline: Delay, worker: F) -> Self {
+        Self::spawn(worker).with_deadline(deadline)
+    }
+
+    /// Spawns `worker`, recording how long it waited in the queue before starting and how long it ran.
+    #[must_use]
+    #[inline]
+    pub fn spawn_timed<F: FnOnce() -> R + Send + 'static>(worker: F) -> Pending<TimedTaskResult<R>> {
+        let queued_at = Instant::now();
+        Pending::spawn(move || {
+            let queued = queued_at.elapsed();
+            let TimedResult { result, elapsed } = crate::time::time_it(worker);
+            TimedTaskResult { result, queued, elapsed }
+        })
+    }
+
+    /// Spawns `worker` on `executor`, such as a specific [rayon::ThreadPool].
+    #[must_use]
+    #[inline]
+    pub fn spawn_on<E: Executor + ?Sized, F: FnOnce() -> R + Send + 'static>(executor: &E, worker: F) -> Self {
         let (pending, responder) = Self::pair();
-        rayon::spawn(#[inline(always)] move || {
+        executor.execute(Box::new(move || {
             responder.respond(worker());
+        }));
+        pending
+    }
+
+    /// Like [Pending::spawn], but a panic in `worker` is caught and delivered to the
+    /// receiver as `Err(payload)`, the same way [std::thread::JoinHandle::join] does.
+    /// 
+    /// The payload can be inspected or re-raised with [std::panic::resume_unwind].
+    #[must_use]
+    #[inline]
+    pub fn spawn_catching<F: FnOnce() -> R + Send + 'static>(worker: F) -> Pending<std::thread::Result<R>> {
+        let (pending, responder) = Pending::pair();
+        rayon::spawn(move || {
+            responder.respond(std::panic::catch_unwind(std::panic::AssertUnwindSafe(worker)));
         });
         pending
     }
 
+}
+
+impl<R: Send> Pending<R> {
+    /// Returns `true` if [Pending::try_recv] would return the value.
     #[inline]
     pub fn is_ready(&self) -> bool {
         let inner_ref = unsafe {
             self.raw.as_ref()
         };
+        // a value that arrived after cancellation is never handed out.
         inner_ref.state.compare_exchange(READY, READY, Ordering::AcqRel, Ordering::Relaxed).is_ok()
+        && !inner_ref.cancel_token().activated()
+    }
+
+    /// Returns `true` if the [Responder] was dropped without responding, meaning
+    /// that the value will never arrive.
+    #[inline]
+    pub fn is_disconnected(&self) -> bool {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        inner_ref.state.load(Ordering::Acquire) == DISCONNECTED
+    }
+
+    /// Returns `true` if [Pending::try_recv] would no longer return [PendingError::Waiting]
+    /// or [PendingError::Assigning]. Unlike `try_recv`, this never changes the state.
+    fn is_resolved(&self) -> bool {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        !matches!(inner_ref.state.load(Ordering::Acquire), WAITING | ASSIGNING)
+        || inner_ref.cancel_token().activated()
+        || self.deadline.is_some_and(Delay::is_ready)
+    }
+
+    /// Cancels the work. The [Responder] can observe this with [Responder::is_cancelled].
+    /// 
+    /// Returns `true` if this call cancelled the work, or `false` if it was already cancelled.
+    #[inline]
+    pub fn cancel(&self) -> bool {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        let cancelled = inner_ref.cancel_token().activate();
+        if cancelled {
+            // wake anything waiting so that it can see the cancellation.
+            inner_ref.wake_receiver();
+        }
+        cancelled
+    }
+
+    /// Returns `true` if the work has been cancelled. Timing out also cancels the work,
+    /// so this is `true` once this [Pending] has resolved to [PendingError::TimedOut].
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        inner_ref.cancel_token().activated()
+    }
+
+    /// Sets the deadline after which this [Pending] resolves to [PendingError::TimedOut].
+    /// If there is already an earlier deadline, that one is kept.
+    /// 
+    /// Whatever is waiting on this [Pending] (a blocking receive, a task awaiting it, a combinator,
+    /// or a [PendingSet](super::pending_set::PendingSet)) is woken once the deadline is ready.
+    pub fn set_deadline(&mut self, deadline: Delay) {
+        if self.deadline.is_some_and(|current| current.deadline() <= deadline.deadline()) {
+            return;
+        }
+        self.deadline = Some(deadline);
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        // a waker registered before the deadline was set doesn't know about it yet.
+        let waker = inner_ref.waker.lock().unwrap_or_else(|err| err.into_inner()).clone();
+        if let Some(waker) = waker && !deadline.is_ready() {
+            inner_ref.arm_deadline_timer(deadline, waker);
+        }
+    }
+
+    /// Makes this [Pending] resolve to [PendingError::TimedOut] once `deadline` is ready.
+    /// See [Pending::set_deadline].
+    #[must_use]
+    #[inline]
+    pub fn with_deadline(mut self, deadline: Delay) -> Self {
+        self.set_deadline(deadline);
+        self
+    }
+
+    #[inline]
+    pub fn deadline(&self) -> Option<Delay> {
+        self.deadline
+    }
+
+    /// Sets whether dropping this [Pending] cancels the work.
+    #[inline]
+    pub fn set_cancel_on_drop(&mut self, cancel_on_drop: bool) {
+        self.cancel_on_drop = cancel_on_drop;
+    }
+
+    /// Makes dropping this [Pending] cancel the work.
+    #[must_use]
+    #[inline]
+    pub fn cancel_on_drop(mut self) -> Self {
+        self.cancel_on_drop = true;
+        self
     }
 
     #[must_use]
@@ -214,28 +747,223 @@ impl<R: Send + 'static> Pending<R> {
     pub fn try_recv(&self) -> std::result::Result<R, PendingError> {
         unsafe {
             let inner_ref = self.raw.as_ref();
+            if self.deadline.is_some_and(Delay::is_ready) {
+                inner_ref.time_out();
+            }
+            if inner_ref.cancel_token().activated() {
+                // a value that was taken before cancellation stays taken.
+                return match inner_ref.state.load(Ordering::Acquire) {
+                    TAKEN => Err(PendingError::Taken),
+                    TIMED_OUT => Err(PendingError::TimedOut),
+                    _ => Err(PendingError::Cancelled),
+                };
+            }
             match inner_ref.state.compare_exchange(READY, TAKEN, Ordering::AcqRel, Ordering::Relaxed) {
                 Ok(_) => Ok(inner_ref.result.get().read().assume_init()),
                 Err(TAKEN) => Err(PendingError::Taken),
                 Err(WAITING) => Err(PendingError::Waiting),
                 Err(ASSIGNING) => Err(PendingError::Assigning),
+                Err(DISCONNECTED) => Err(PendingError::Disconnected),
+                Err(TIMED_OUT) => Err(PendingError::TimedOut),
                 Err(_) => unreachable!("Corrupted state; should not be possible."),
             }
         }
     }
+
+    /// Blocks the current thread until the value is ready.
+    #[inline]
+    pub fn recv(mut self) -> std::result::Result<R, PendingError> {
+        self.recv_until_instant(None)
+    }
+
+    /// Blocks the current thread until the value is ready or `timeout` has elapsed.
+    /// 
+    /// Returns [PendingError::TimedOut] if the value was not ready in time.
+    #[inline]
+    pub fn recv_timeout(&mut self, timeout: Duration) -> std::result::Result<R, PendingError> {
+        self.recv_until_instant(Instant::now().checked_add(timeout))
+    }
+
+    /// Blocks the current thread until the value is ready or `deadline` is ready.
+    /// 
+    /// Returns [PendingError::TimedOut] if the value was not ready in time.
+    #[inline]
+    pub fn recv_deadline(&mut self, deadline: Delay) -> std::result::Result<R, PendingError> {
+        self.recv_until_instant(Some(deadline.deadline()))
+    }
+
+    /// Gives up on the value once `deadline` is ready, resolving to [PendingError::TimedOut].
+    /// 
+    /// Unlike [Pending::recv_deadline], the timeout is final: the deadline is kept by this
+    /// [Pending], a value that arrives late is discarded, and the worker sees
+    /// [Responder::is_cancelled].
+    #[inline]
+    pub fn recv_until(&mut self, deadline: Delay) -> std::result::Result<R, PendingError> {
+        self.set_deadline(deadline);
+        self.recv_until_instant(None)
+    }
+
+    // Takes `&mut self` so that only one thread can be registered as the receiver.
+    fn recv_until_instant(&mut self, deadline: Option<Instant>) -> std::result::Result<R, PendingError> {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        let mut waker = None;
+        loop {
+            match self.try_recv() {
+                Err(PendingError::Waiting | PendingError::Assigning) => (),
+                result => return result,
+            }
+            // Register, then check again so that a response that arrives
+            // between the check and the registration is not missed.
+            if waker.is_none() {
+                let thread_waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+                inner_ref.register_waker(&thread_waker);
+                waker = Some(thread_waker);
+                continue;
+            }
+            if let Some(deadline) = deadline && Instant::now() >= deadline {
+                inner_ref.unregister_waker();
+                return Err(PendingError::TimedOut);
+            }
+            // On a rayon worker, the job this waits on may be queued behind it on the same worker
+            // (as in a [scope]), so run queued jobs before parking.
+            if let Some(rayon::Yield::Executed) = rayon::yield_now() {
+                continue;
+            }
+            // Wake up for whichever comes first, `deadline` or this `Pending`'s own deadline.
+            let wake_at = match (deadline, self.deadline.map(Delay::deadline)) {
+                (Some(lhs), Some(rhs)) => Some(lhs.min(rhs)),
+                (lhs, rhs) => lhs.or(rhs),
+            };
+            match wake_at {
+                Some(wake_at) => std::thread::park_timeout(wake_at.saturating_duration_since(Instant::now())),
+                None => std::thread::park(),
+            }
+        }
+    }
 }
 
-impl<R: Send + 'static> Drop for Pending<R> {
+// Combinators
+impl<R: Send + 'static> Pending<R> {
+    /// Registers `waker` to be woken once this [Pending] resolves without taking the value.
+    /// If it has already resolved, `waker` is woken immediately.
+    pub(crate) fn set_waker(&self, waker: &Waker) {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        inner_ref.register_waker_until(waker, self.deadline);
+        if self.is_resolved() {
+            waker.wake_by_ref();
+        }
+    }
+
+    /// Calls `callback` with the result once this [Pending] resolves, on whichever
+    /// thread resolves it (or immediately if it has already resolved).
+    pub(crate) fn on_ready<F: FnOnce(std::result::Result<R, PendingError>) + Send + 'static>(self, callback: F) {
+        let raw = self.raw;
+        let deadline = self.deadline;
+        let continuation = Arc::new(Continuation {
+            slot: Mutex::new(Some((self, Box::new(callback) as Callback<R>))),
+        });
+        let waker = Waker::from(continuation.clone());
+        // SAFETY: the continuation owns the `Pending`, so the allocation is still alive.
+        unsafe {
+            raw.as_ref()
+        }.register_waker_until(&waker, deadline);
+        // In case it resolved before the waker was registered.
+        continuation.wake_by_ref();
+    }
+
+    /// Sends the result of this [Pending] through `responder` once it arrives.
+    /// 
+    /// If this [Pending] fails, `responder` is dropped and its receiver is disconnected.
+    fn forward(self, responder: Responder<R>) {
+        self.on_ready(move |result| {
+            if let Ok(value) = result {
+                responder.respond(value);
+            }
+        });
+    }
+
+    /// Runs `f` on the result once it arrives. `f` is spawned on the rayon pool of the
+    /// thread that resolves this [Pending].
+    /// 
+    /// If this [Pending] fails, the returned [Pending] is disconnected.
+    #[must_use]
+    pub fn map<U: Send + 'static, F: FnOnce(R) -> U + Send + 'static>(self, f: F) -> Pending<U> {
+        let (pending, responder) = Pending::pair();
+        self.on_ready(move |result| {
+            if let Ok(value) = result {
+                rayon::spawn(move || {
+                    responder.respond(f(value));
+                });
+            }
+        });
+        pending
+    }
+
+    /// Runs `f` on the result once it arrives, resolving with the result of the [Pending] that `f` returns.
+    /// `f` is spawned on the rayon pool of the thread that resolves this [Pending].
+    /// 
+    /// If either [Pending] fails, the returned [Pending] is disconnected.
+    #[must_use]
+    pub fn and_then<U: Send + 'static, F: FnOnce(R) -> Pending<U> + Send + 'static>(self, f: F) -> Pending<U> {
+        let (pending, responder) = Pending::pair();
+        self.on_ready(move |result| {
+            if let Ok(value) = result {
+                rayon::spawn(move || {
+                    f(value).forward(responder);
+                });
+            }
+        });
+        pending
+    }
+}
+
+impl<R: Send> Future for Pending<R> {
+    type Output = std::result::Result<R, PendingError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner_ref = unsafe {
+            self.raw.as_ref()
+        };
+        match self.try_recv() {
+            Err(PendingError::Waiting | PendingError::Assigning) => (),
+            result => return Poll::Ready(result),
+        }
+        inner_ref.register_waker_until(cx.waker(), self.deadline);
+        // Check again in case the value arrived before the waker was registered.
+        match self.try_recv() {
+            Err(PendingError::Waiting | PendingError::Assigning) => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+}
+
+impl<R: Send> Drop for Pending<R> {
     fn drop(&mut self) {
+        if self.cancel_on_drop {
+            self.cancel();
+        }
         unsafe {
+            // nothing is left to wake.
+            let inner_ref = self.raw.as_ref();
+            inner_ref.cancel_deadline_timer();
+            inner_ref.unregister_waker();
             Inner::<R>::decrement_ref_count(self.raw);
         }
     }
 }
 
-impl<R: Send + 'static> Drop for Responder<R> {
+impl<R: Send> Drop for Responder<R> {
     fn drop(&mut self) {
         unsafe {
+            let inner_ref = self.raw.as_ref();
+            // If `respond` was never called, let the receiver know that it never will be.
+            if inner_ref.state.compare_exchange(WAITING, DISCONNECTED, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                inner_ref.wake_receiver();
+            }
             Inner::<R>::decrement_ref_count(self.raw);
         }
     }
@@ -243,16 +971,189 @@ impl<R: Send + 'static> Drop for Responder<R> {
 
 #[must_use]
 #[inline]
-pub fn pair<R: Send + 'static>() -> (Pending<R>, Responder<R>) {
+pub fn pair<R: Send>() -> (Pending<R>, Responder<R>) {
     Pending::pair()
 }
 
+#[inline]
+pub fn try_pair<R: Send>() -> Result<(Pending<R>, Responder<R>)> {
+    Pending::try_pair()
+}
+
+#[inline]
+pub fn try_spawn<R: Send + 'static, F: FnOnce() -> R + Send + 'static>(worker: F) -> Result<Pending<R>> {
+    Pending::try_spawn(worker)
+}
+
 #[must_use]
 #[inline]
 pub fn spawn<R: Send + 'static, F: FnOnce() -> R + Send + 'static>(worker: F) -> Pending<R> {
     Pending::spawn(worker)
 }
 
+#[must_use]
+#[inline]
+pub fn spawn_with<S: SpawnStrategy, R: Send + 'static, F: FnOnce() -> R + Send + 'static>(worker: F) -> Pending<R> {
+    Pending::spawn_with::<S, F>(worker)
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_on<E: Executor + ?Sized, R: Send + 'static, F: FnOnce() -> R + Send + 'static>(executor: &E, worker: F) -> Pending<R> {
+    Pending::spawn_on(executor, worker)
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_cancellable<R: Send + 'static, F: FnOnce(CancelToken<'_>) -> R + Send + 'static>(worker: F) -> Pending<R> {
+    Pending::spawn_cancellable(worker)
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_with_deadline<R: Send + 'static, F: FnOnce() -> R + Send + 'static>(deadline: Delay, worker: F) -> Pending<R> {
+    Pending::spawn_with_deadline(deadline, worker)
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_timed<R: Send + 'static, F: FnOnce() -> R + Send + 'static>(worker: F) -> Pending<TimedTaskResult<R>> {
+    Pending::spawn_timed(worker)
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_catching<R: Send + 'static, F: FnOnce() -> R + Send + 'static>(worker: F) -> Pending<std::thread::Result<R>> {
+    Pending::spawn_catching(worker)
+}
+
+/// Spawns [Pending]s that can borrow from outside of the scope. See [scope].
+pub struct Scope<'a, 'scope> {
+    inner: &'a rayon::Scope<'scope>,
+}
+
+impl<'scope> Scope<'_, 'scope> {
+    /// Spawns `worker` on the rayon pool. The scope doesn't return until `worker` has finished.
+    /// 
+    /// Inside a rayon worker, `worker` is queued on the current worker thread. Receiving the
+    /// [Pending] there runs queued jobs while it waits, so that doesn't deadlock.
+    #[must_use]
+    pub fn spawn<R: Send + 'scope, F: FnOnce() -> R + Send + 'scope>(&self, worker: F) -> Pending<R> {
+        let (pending, responder) = Pending::pair();
+        self.inner.spawn(move |_| {
+            responder.respond(worker());
+        });
+        pending
+    }
+}
+
+impl std::fmt::Debug for Scope<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scope").finish_non_exhaustive()
+    }
+}
+
+/// Creates a scope for spawning [Pending]s whose workers can borrow non-`'static` data,
+/// like [std::thread::scope]. Every worker spawned in the scope has finished by the time
+/// [scope] returns.
+/// 
+/// `op` runs on the calling thread.
+/// 
+/// # Example
+/// ```rust
+/// let numbers = vec![1, 2, 3, 4, 5, 6];
+/// let (lhs, rhs) = numbers.split_at(3);
+/// let sum = dmf::concurrency::pending::scope(|s| {
+///     let lhs = s.spawn(|| lhs.iter().sum::<i32>());
+///     let rhs = s.spawn(|| rhs.iter().sum::<i32>());
+///     lhs.recv().unwrap() + rhs.recv().unwrap()
+/// });
+/// assert_eq!(sum, 21);
+/// ```
+pub fn scope<'scope, OP, T>(op: OP) -> T
+where OP: FnOnce(&Scope<'_, 'scope>) -> T {
+    rayon::in_place_scope(|inner| op(&Scope { inner }))
+}
+
+/// Resolves with every result, in the same order as `pendings`.
+/// 
+/// If any of the pendings fail, the returned [Pending] is disconnected.
+#[must_use]
+pub fn join_all<R: Send + 'static>(pendings: Vec<Pending<R>>) -> Pending<Vec<R>> {
+    struct JoinAll<R: Send + 'static> {
+        results: Mutex<Vec<Option<R>>>,
+        remaining: std::sync::atomic::AtomicUsize,
+        responder: Mutex<Option<Responder<Vec<R>>>>,
+    }
+    let (pending, responder) = Pending::pair();
+    if pendings.is_empty() {
+        responder.respond(Vec::new());
+        return pending;
+    }
+    let join = Arc::new(JoinAll {
+        results: Mutex::new(pendings.iter().map(|_| None).collect()),
+        remaining: std::sync::atomic::AtomicUsize::new(pendings.len()),
+        responder: Mutex::new(Some(responder)),
+    });
+    for (index, source) in pendings.into_iter().enumerate() {
+        let join = join.clone();
+        source.on_ready(move |result| {
+            let mut responder = join.responder.lock().unwrap_or_else(|err| err.into_inner());
+            let Ok(value) = result else {
+                // dropping the responder disconnects the receiver.
+                responder.take();
+                return;
+            };
+            let mut results = join.results.lock().unwrap_or_else(|err| err.into_inner());
+            results[index] = Some(value);
+            if join.remaining.fetch_sub(1, Ordering::AcqRel) == 1
+            && let Some(responder) = responder.take() {
+                responder.respond(results.drain(..).flatten().collect());
+            }
+        });
+    }
+    pending
+}
+
+/// Resolves with both results once both have arrived.
+/// 
+/// If either [Pending] fails, the returned [Pending] is disconnected.
+#[must_use]
+pub fn join2<A: Send + 'static, B: Send + 'static>(a: Pending<A>, b: Pending<B>) -> Pending<(A, B)> {
+    a.and_then(move |a| b.map(move |b| (a, b)))
+}
+
+/// Resolves with all three results once they have arrived.
+/// 
+/// If any [Pending] fails, the returned [Pending] is disconnected.
+#[must_use]
+pub fn join3<A: Send + 'static, B: Send + 'static, C: Send + 'static>(a: Pending<A>, b: Pending<B>, c: Pending<C>) -> Pending<(A, B, C)> {
+    join2(a, b).and_then(move |(a, b)| c.map(move |c| (a, b, c)))
+}
+
+/// Resolves with the index and result of the first [Pending] to succeed. The other results are discarded.
+/// 
+/// If all of the pendings fail, the returned [Pending] is disconnected.
+#[must_use]
+pub fn select_any<R: Send + 'static>(pendings: Vec<Pending<R>>) -> Pending<(usize, R)> {
+    let (pending, responder) = Pending::pair();
+    // The responder is dropped (disconnecting the receiver) when the last callback
+    // releases it without anything having succeeded.
+    let responder = Arc::new(Mutex::new(Some(responder)));
+    for (index, source) in pendings.into_iter().enumerate() {
+        let responder = responder.clone();
+        source.on_ready(move |result| {
+            if let Ok(value) = result {
+                let first = responder.lock().unwrap_or_else(|err| err.into_inner()).take();
+                if let Some(first) = first {
+                    first.respond((index, value));
+                }
+            }
+        });
+    }
+    pending
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -277,4 +1178,329 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn recv_test() {
+        let (pending, responder) = Pending::pair();
+        let start = std::time::Instant::now();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            responder.respond(1234u32);
+        });
+        assert_eq!(pending.recv(), Ok(1234));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    // Minimal executor: poll on the current thread and park until woken.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn future_test() {
+        let pending = Pending::spawn(|| {
+            std::thread::sleep(Duration::from_millis(100));
+            String::from("async")
+        });
+        let result = block_on(async move {
+            let value = pending.await?;
+            Ok::<_, PendingError>(value + "!")
+        });
+        assert_eq!(result.as_deref(), Ok("async!"));
+    }
+
+    #[test]
+    fn disconnected_test() {
+        let (pending, responder) = Pending::<u32>::pair();
+        assert_eq!(pending.try_recv(), Err(PendingError::Waiting));
+        drop(responder);
+        assert!(pending.is_disconnected());
+        assert!(!pending.is_ready());
+        assert_eq!(pending.try_recv(), Err(PendingError::Disconnected));
+
+        let (pending, responder) = Pending::<u32>::pair();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            drop(responder);
+        });
+        assert_eq!(pending.recv(), Err(PendingError::Disconnected));
+
+        let (pending, responder) = Pending::pair();
+        responder.respond(7u32);
+        assert!(!pending.is_disconnected());
+        assert_eq!(pending.try_recv(), Ok(7));
+    }
+
+    #[test]
+    fn spawn_catching_test() {
+        let pending = Pending::spawn_catching(|| -> u32 {
+            panic!("worker failed");
+        });
+        let payload = pending.recv().unwrap().unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"worker failed"));
+
+        let pending = spawn_catching(|| 5u32);
+        assert_eq!(pending.recv().unwrap().unwrap(), 5);
+    }
+
+    #[test]
+    fn spawn_strategy_test() {
+        let caller = std::thread::current().id();
+        let pending = Pending::spawn_with::<StdThread, _>(move || std::thread::current().id() != caller);
+        assert_eq!(pending.recv(), Ok(true));
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).thread_name(|_| String::from("custom pool")).build().unwrap();
+        let pending = Pending::spawn_on(&pool, || std::thread::current().name().map(String::from));
+        assert_eq!(pending.recv(), Ok(Some(String::from("custom pool"))));
+
+        struct Inline;
+        impl Executor for Inline {
+            fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+                job();
+            }
+        }
+        let executor: &dyn Executor = &Inline;
+        let pending = spawn_on(executor, || 3u8);
+        assert!(pending.is_ready());
+        assert_eq!(pending.try_recv(), Ok(3));
+    }
+
+    #[test]
+    fn cancel_test() {
+        let (started, start_rx) = std::sync::mpsc::channel();
+        let pending = Pending::spawn_cancellable(move |token| {
+            started.send(()).unwrap();
+            let mut iterations = 0u64;
+            while !token.is_cancelled() {
+                iterations += 1;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            iterations
+        });
+        start_rx.recv().unwrap();
+        assert!(pending.cancel());
+        assert!(!pending.cancel());
+        assert!(pending.is_cancelled());
+        assert_eq!(pending.recv(), Err(PendingError::Cancelled));
+
+        let (pending, responder) = Pending::<()>::pair();
+        let pending = pending.cancel_on_drop();
+        assert!(!responder.is_cancelled());
+        drop(pending);
+        assert!(responder.is_cancelled());
+
+        let (pending, responder) = Pending::pair();
+        assert!(pending.cancel());
+        responder.respond(1u8);
+        assert!(!pending.is_ready());
+        assert_eq!(pending.try_recv(), Err(PendingError::Cancelled));
+
+        let (pending, responder) = Pending::<()>::pair();
+        let token = responder.cancel_token();
+        assert!(!token.is_cancelled());
+        assert!(pending.cancel());
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn combinator_test() {
+        let pending = Pending::spawn(|| 20u32).map(|n| n + 1).map(|n| n.to_string());
+        assert_eq!(pending.recv().as_deref(), Ok("21"));
+
+        let pending = Pending::spawn(|| 3u64).and_then(|n| Pending::spawn(move || n * 7));
+        assert_eq!(pending.recv(), Ok(21));
+
+        let (failing, responder) = Pending::<u32>::pair();
+        drop(responder);
+        assert_eq!(failing.map(|n| n * 2).recv(), Err(PendingError::Disconnected));
+
+        let pendings = (0..16u64).map(|i| Pending::spawn(move || {
+            std::thread::sleep(Duration::from_millis(16 - i));
+            i * i
+        })).collect();
+        assert_eq!(join_all(pendings).recv(), Ok((0..16u64).map(|i| i * i).collect()));
+        assert_eq!(join_all(Vec::<Pending<u8>>::new()).recv(), Ok(vec![]));
+
+        let (failing, responder) = Pending::<u32>::pair();
+        drop(responder);
+        assert_eq!(join_all(vec![Pending::spawn(|| 1), failing]).recv(), Err(PendingError::Disconnected));
+
+        let joined = join3(Pending::spawn(|| 1u8), Pending::spawn(|| "two"), Pending::spawn(|| 3.0f32));
+        assert_eq!(joined.recv(), Ok((1, "two", 3.0)));
+
+        let (slow, slow_responder) = Pending::pair();
+        let (failing, failing_responder) = Pending::pair();
+        let selected = select_any(vec![slow, failing, Pending::spawn(|| "fast")]);
+        drop(failing_responder);
+        assert_eq!(selected.recv(), Ok((2, "fast")));
+        slow_responder.respond("slow");
+
+        let (failing, responder) = Pending::<u32>::pair();
+        let selected = select_any(vec![failing]);
+        drop(responder);
+        assert_eq!(selected.recv(), Err(PendingError::Disconnected));
+        assert_eq!(select_any(Vec::<Pending<u8>>::new()).recv(), Err(PendingError::Disconnected));
+    }
+
+    #[test]
+    fn expired_deadline_combinator_test() {
+        // timing out on registration wakes the continuation that is checking the result.
+        let (expired, _responder) = Pending::<u32>::pair();
+        let mapped = expired.with_deadline(Delay::millis(0)).map(|n| n + 1);
+        assert_eq!(mapped.recv(), Err(PendingError::Disconnected));
+
+        let (expired, responder) = Pending::<u32>::pair();
+        let joined = join_all(vec![Pending::spawn(|| 1), expired.with_deadline(Delay::millis(0))]);
+        assert_eq!(joined.recv(), Err(PendingError::Disconnected));
+        responder.respond(2);
+
+        let (expired, _responder) = Pending::<u32>::pair();
+        let selected = select_any(vec![expired.with_deadline(Delay::millis(0)), Pending::spawn(|| 3)]);
+        assert_eq!(selected.recv(), Ok((1, 3)));
+    }
+
+    #[test]
+    fn pool_test() {
+        let pool = PendingPool::<String>::new(2);
+        pool.try_reserve(8).unwrap();
+        assert_eq!(pool.free_slots(), 2);
+        let pairs = (0..4).map(|_| pool.try_pair().unwrap()).collect::<Vec<_>>();
+        assert_eq!(pool.free_slots(), 0);
+        for (i, (pending, responder)) in pairs.into_iter().enumerate() {
+            if i % 2 == 0 {
+                responder.respond(i.to_string());
+            } else {
+                drop(responder);
+            }
+            drop(pending);
+        }
+        assert_eq!(pool.free_slots(), 2);
+        let pending = pool.try_spawn(|| String::from("recycled")).unwrap();
+        assert_eq!(pending.recv().as_deref(), Ok("recycled"));
+        assert_eq!(pool.free_slots(), 2);
+        // outstanding pairs keep the free list alive after the pool is dropped.
+        let (pending, responder) = pool.pair();
+        drop(pool);
+        responder.respond(String::from("late"));
+        assert_eq!(pending.recv().as_deref(), Ok("late"));
+
+        let pending = try_spawn(|| 1u8).unwrap();
+        assert_eq!(pending.recv(), Ok(1));
+    }
+
+    #[test]
+    fn deadline_test() {
+        let (started, start_rx) = std::sync::mpsc::channel();
+        let (finished, finish_rx) = std::sync::mpsc::channel();
+        let (mut pending, responder) = Pending::pair();
+        std::thread::spawn(move || {
+            started.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            finished.send(responder.is_cancelled()).unwrap();
+            responder.respond(String::from("late"));
+        });
+        start_rx.recv().unwrap();
+        assert_eq!(pending.recv_until(Delay::millis(20)), Err(PendingError::TimedOut));
+        assert!(finish_rx.recv().unwrap());
+        assert_eq!(pending.try_recv(), Err(PendingError::TimedOut));
+        assert!(pending.is_cancelled());
+
+        let mut pending = Pending::spawn_with_deadline(Delay::millis(20), || {
+            std::thread::sleep(Duration::from_millis(200));
+        });
+        assert_eq!(pending.recv_timeout(Duration::from_secs(10)), Err(PendingError::TimedOut));
+        assert_eq!(pending.try_recv(), Err(PendingError::TimedOut));
+
+        let pending = spawn_with_deadline(Delay::secs(10), || 10u32);
+        assert_eq!(pending.recv(), Ok(10));
+
+        // nothing polls these, so only the deadline can wake them.
+        let (pending, _responder) = Pending::<u32>::pair();
+        let mapped = pending.with_deadline(Delay::millis(20)).map(|n| n + 1);
+        assert_eq!(mapped.recv(), Err(PendingError::Disconnected));
+
+        let (pending, _responder) = Pending::<u32>::pair();
+        assert_eq!(block_on(pending.with_deadline(Delay::millis(20))), Err(PendingError::TimedOut));
+
+        // the deadline wakeup lets go of the waker once it isn't needed.
+        let thread_waker = Arc::new(ThreadWaker(std::thread::current()));
+        let waker = Waker::from(thread_waker.clone());
+        for resolve in [true, false] {
+            let (pending, responder) = Pending::<u32>::pair();
+            let mut pending = pending.with_deadline(Delay::secs(60));
+            assert!(Pin::new(&mut pending).poll(&mut Context::from_waker(&waker)).is_pending());
+            assert_eq!(Arc::strong_count(&thread_waker), 4);
+            if resolve {
+                responder.respond(1);
+            } else {
+                drop(pending);
+            }
+            assert_eq!(Arc::strong_count(&thread_waker), 2);
+        }
+
+        let (pending, responder) = Pending::pair();
+        responder.respond(5u8);
+        let pending = pending.with_deadline(Delay::millis(0));
+        assert_eq!(pending.try_recv(), Ok(5));
+    }
+
+    #[test]
+    fn spawn_timed_test() {
+        // occupy the pool so that the timed task has to wait in the queue.
+        let blockers = (0..rayon::current_num_threads()).map(|_| {
+            Pending::spawn(|| std::thread::sleep(Duration::from_millis(100)))
+        }).collect::<Vec<_>>();
+        let timed = spawn_timed(|| {
+            std::thread::sleep(Duration::from_millis(50));
+            "timed"
+        }).recv().unwrap();
+        assert_eq!(timed.result, "timed");
+        assert!(timed.elapsed >= Duration::from_millis(50));
+        assert!(timed.queued >= Duration::from_millis(50));
+        assert_eq!(timed.total(), timed.queued + timed.elapsed);
+        assert!(join_all(blockers).recv().is_ok());
+    }
+
+    #[test]
+    fn scope_test() {
+        let mut words = vec![String::from("borrowed"), String::from("data")];
+        let counts = scope(|s| {
+            let pendings = words.iter().map(|word| s.spawn(|| word.as_str())).collect::<Vec<_>>();
+            let total = s.spawn(|| words.iter().map(String::len).sum::<usize>());
+            (pendings.into_iter().map(|pending| pending.recv().unwrap()).collect::<Vec<&str>>(), total)
+        });
+        assert_eq!(counts.0, ["borrowed", "data"]);
+        // the scope has finished, so the worker is done even though nobody waited on it.
+        assert_eq!(counts.1.try_recv(), Ok(12));
+        words.clear();
+
+        // with a single worker, the scoped jobs can only run while `recv` waits on them.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let sum = pool.install(|| scope(|s| {
+            let lhs = s.spawn(|| 1);
+            let rhs = s.spawn(|| 2);
+            lhs.recv().unwrap() + rhs.recv().unwrap()
+        }));
+        assert_eq!(sum, 3);
+    }
+
+    #[test]
+    fn recv_timeout_test() {
+        let (mut pending, responder) = Pending::<&'static str>::pair();
+        assert_eq!(pending.recv_timeout(Duration::from_millis(50)), Err(PendingError::TimedOut));
+        assert_eq!(pending.recv_deadline(Delay::millis(50)), Err(PendingError::TimedOut));
+        std::thread::spawn(move || {
+            responder.respond("done");
+        });
+        assert_eq!(pending.recv_timeout(Duration::from_secs(5)), Ok("done"));
+        assert_eq!(pending.try_recv(), Err(PendingError::Taken));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,259 @@
+use std::{
+    collections::{HashMap, VecDeque}, hash::Hash, sync::{Arc, Condvar, Mutex, MutexGuard}, task::{Wake, Waker}, time::{Duration, Instant}
+};
+
+use super::pending::{Pending, PendingError};
+
+/// Keys of pendings that have (probably) resolved, in the order they resolved.
+struct ReadyQueue<K> {
+    keys: Mutex<VecDeque<K>>,
+    signal: Condvar,
+}
+
+impl<K> ReadyQueue<K> {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<K>> {
+        self.keys.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Pushes its key onto the ready queue when the [Pending] resolves.
+struct KeyWaker<K> {
+    key: K,
+    ready: Arc<ReadyQueue<K>>,
+}
+
+impl<K: Clone + Send + Sync + 'static> Wake for KeyWaker<K> {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().push_back(self.key.clone());
+        self.ready.signal.notify_one();
+    }
+}
+
+/// Owns many [Pending]s keyed by an id and yields their results in the order that they complete.
+pub struct PendingSet<K, R>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    R: Send + 'static {
+    pendings: HashMap<K, Pending<R>>,
+    ready: Arc<ReadyQueue<K>>,
+}
+
+impl<K, R> Default for PendingSet<K, R>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    R: Send + 'static {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, R> std::fmt::Debug for PendingSet<K, R>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    R: Send + 'static {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingSet")
+            .field("outstanding", &self.len())
+            .finish()
+    }
+}
+
+impl<K, R> PendingSet<K, R>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    R: Send + 'static {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            pendings: HashMap::new(),
+            ready: Arc::new(ReadyQueue {
+                keys: Mutex::new(VecDeque::new()),
+                signal: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Adds `pending` to the set under `key`, returning the [Pending] it replaced, if any.
+    pub fn insert(&mut self, key: K, pending: Pending<R>) -> Option<Pending<R>> {
+        let waker = Waker::from(Arc::new(KeyWaker {
+            key: key.clone(),
+            ready: self.ready.clone(),
+        }));
+        pending.set_waker(&waker);
+        self.pendings.insert(key, pending)
+    }
+
+    /// Spawns `worker` with [Pending::spawn] and adds it to the set under `key`.
+    pub fn spawn<F: FnOnce() -> R + Send + 'static>(&mut self, key: K, worker: F) -> Option<Pending<R>> {
+        self.insert(key, Pending::spawn(worker))
+    }
+
+    /// Removes the [Pending] under `key` from the set without cancelling it.
+    #[inline]
+    pub fn remove(&mut self, key: &K) -> Option<Pending<R>> {
+        self.pendings.remove(key)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.pendings.contains_key(key)
+    }
+
+    /// The number of outstanding pendings.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pendings.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pendings.is_empty()
+    }
+
+    /// Tries to take the result for `key` if it has been queued as ready.
+    fn take_ready(&mut self, key: K) -> Option<(K, Result<R, PendingError>)> {
+        // Keys can be stale (removed, or replaced with a new `Pending`), so the
+        // `Pending` is checked before it is taken out of the set.
+        let pending = self.pendings.get(&key)?;
+        match pending.try_recv() {
+            Err(PendingError::Waiting | PendingError::Assigning) => None,
+            result => {
+                self.pendings.remove(&key);
+                Some((key, result))
+            }
+        }
+    }
+
+    /// Returns the next completed result, in completion order, if there is one.
+    pub fn next_ready(&mut self) -> Option<(K, Result<R, PendingError>)> {
+        loop {
+            let key = self.ready.lock().pop_front()?;
+            if let Some(ready) = self.take_ready(key) {
+                return Some(ready);
+            }
+        }
+    }
+
+    /// Blocks until one of the pendings completes and returns its result.
+    /// 
+    /// Returns `None` if the set is empty.
+    pub fn wait_any(&mut self) -> Option<(K, Result<R, PendingError>)> {
+        loop {
+            if self.pendings.is_empty() {
+                return None;
+            }
+            let key = {
+                let keys = self.ready.lock();
+                let mut keys = self.ready.signal.wait_while(keys, |keys| keys.is_empty())
+                    .unwrap_or_else(|err| err.into_inner());
+                keys.pop_front()
+            };
+            if let Some(ready) = key.and_then(|key| self.take_ready(key)) {
+                return Some(ready);
+            }
+        }
+    }
+
+    /// Blocks until one of the pendings completes or `timeout` has elapsed.
+    /// 
+    /// Returns `None` if the set is empty or nothing completed in time.
+    pub fn wait_any_timeout(&mut self, timeout: Duration) -> Option<(K, Result<R, PendingError>)> {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            if self.pendings.is_empty() {
+                return None;
+            }
+            let key = {
+                let mut keys = self.ready.lock();
+                while keys.is_empty() {
+                    let remaining = match deadline {
+                        Some(deadline) => deadline.checked_duration_since(Instant::now())?,
+                        None => Duration::MAX,
+                    };
+                    keys = self.ready.signal.wait_timeout(keys, remaining)
+                        .unwrap_or_else(|err| err.into_inner()).0;
+                }
+                keys.pop_front()
+            };
+            if let Some(ready) = key.and_then(|key| self.take_ready(key)) {
+                return Some(ready);
+            }
+        }
+    }
+
+    /// Cancels every outstanding [Pending] and removes them from the set.
+    pub fn cancel_all(&mut self) {
+        for (_, pending) in self.pendings.drain() {
+            pending.cancel();
+        }
+        self.ready.lock().clear();
+    }
+
+    /// Removes every outstanding [Pending] from the set without cancelling them.
+    pub fn detach_all(&mut self) -> Vec<(K, Pending<R>)> {
+        self.ready.lock().clear();
+        self.pendings.drain().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_set_test() {
+        let mut set = PendingSet::new();
+        let mut responders = Vec::new();
+        for i in 0..8u64 {
+            let (pending, responder) = Pending::pair();
+            set.insert(i, pending);
+            responders.push((i, responder));
+        }
+        assert_eq!(set.len(), 8);
+        assert!(set.next_ready().is_none());
+        std::thread::spawn(move || {
+            for (i, responder) in responders.into_iter().rev() {
+                std::thread::sleep(Duration::from_millis(5));
+                responder.respond(i);
+            }
+        });
+        let mut order = Vec::new();
+        while let Some((key, result)) = set.wait_any() {
+            assert_eq!(Ok(key), result);
+            order.push(key);
+        }
+        assert_eq!(order, (0..8).rev().collect::<Vec<_>>());
+        assert!(set.is_empty());
+
+        let (pending, responder) = Pending::pair();
+        set.insert(100, pending);
+        set.spawn(200, || 200);
+        let (key, result) = set.wait_any_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((key, result), (200, Ok(200)));
+        assert!(set.wait_any_timeout(Duration::from_millis(20)).is_none());
+        drop(responder);
+        assert_eq!(set.wait_any(), Some((100, Err(PendingError::Disconnected))));
+
+        let (pending, responder) = Pending::pair();
+        set.insert(1, pending);
+        let detached = set.detach_all();
+        assert!(set.is_empty());
+        assert_eq!(detached.len(), 1);
+        responder.respond(1);
+        assert_eq!(detached[0].1.try_recv(), Ok(1));
+
+        let (pending, responder) = Pending::pair();
+        set.insert(2, pending);
+        set.cancel_all();
+        assert!(responder.is_cancelled());
+        assert!(set.next_ready().is_none());
+
+        let (pending, _responder) = Pending::<u64>::pair();
+        set.insert(3, pending.with_deadline(crate::time::Delay::millis(20)));
+        assert_eq!(set.wait_any(), Some((3, Err(PendingError::TimedOut))));
+    }
+}
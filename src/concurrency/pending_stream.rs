@@ -0,0 +1,239 @@
+use std::{
+    collections::VecDeque, sync::{Arc, Condvar, Mutex, MutexGuard}, time::{Duration, Instant}
+};
+
+use crate::time::Delay;
+
+use super::pending::PendingError;
+
+struct State<T> {
+    items: VecDeque<T>,
+    // The `StreamResponder` has been dropped.
+    finished: bool,
+    // The `PendingStream` has been dropped.
+    closed: bool,
+}
+
+struct Channel<T> {
+    state: Mutex<State<T>>,
+    available: Condvar,
+}
+
+impl<T> Channel<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The receiving half of a stream of values sent by a [StreamResponder].
+/// 
+/// The stream ends once the [StreamResponder] is dropped and every sent value has been received.
+pub struct PendingStream<T: Send + 'static> {
+    channel: Arc<Channel<T>>,
+}
+
+/// The sending half of a [PendingStream]. Dropping it ends the stream.
+pub struct StreamResponder<T: Send + 'static> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T: Send + 'static> std::fmt::Debug for PendingStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.channel.lock();
+        f.debug_struct("PendingStream")
+            .field("buffered", &state.items.len())
+            .field("finished", &state.finished)
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> std::fmt::Debug for StreamResponder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamResponder")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> StreamResponder<T> {
+    /// Sends `item` to the [PendingStream].
+    /// 
+    /// Returns `Err(item)` if the [PendingStream] has been dropped.
+    pub fn send(&self, item: T) -> Result<(), T> {
+        {
+            let mut state = self.channel.lock();
+            if state.closed {
+                return Err(item);
+            }
+            state.items.push_back(item);
+        }
+        self.channel.available.notify_one();
+        Ok(())
+    }
+
+    /// Ends the stream. This is the same as dropping the [StreamResponder].
+    #[inline]
+    pub fn finish(self) {}
+
+    /// Returns `true` if the [PendingStream] has been dropped, meaning nothing is listening anymore.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.channel.lock().closed
+    }
+}
+
+impl<T: Send + 'static> PendingStream<T> {
+    #[must_use]
+    pub fn pair() -> (Self, StreamResponder<T>) {
+        let channel = Arc::new(Channel {
+            state: Mutex::new(State {
+                items: VecDeque::new(),
+                finished: false,
+                closed: false,
+            }),
+            available: Condvar::new(),
+        });
+        (
+            Self { channel: channel.clone() },
+            StreamResponder { channel },
+        )
+    }
+
+    /// Spawns `worker` on the rayon pool. The stream ends when `worker` returns.
+    #[must_use]
+    pub fn spawn<F: FnOnce(StreamResponder<T>) + Send + 'static>(worker: F) -> Self {
+        let (stream, responder) = Self::pair();
+        rayon::spawn(move || worker(responder));
+        stream
+    }
+
+    /// Returns `true` if the stream has ended and every value has been received.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        let state = self.channel.lock();
+        state.finished && state.items.is_empty()
+    }
+
+    /// Takes the next value if one is available.
+    /// 
+    /// Returns [PendingError::Waiting] if no value is available yet, or
+    /// [PendingError::Disconnected] once the stream has ended.
+    pub fn try_next(&self) -> Result<T, PendingError> {
+        let mut state = self.channel.lock();
+        match state.items.pop_front() {
+            Some(item) => Ok(item),
+            None if state.finished => Err(PendingError::Disconnected),
+            None => Err(PendingError::Waiting),
+        }
+    }
+
+    /// Blocks the current thread until the next value arrives.
+    /// 
+    /// Returns [PendingError::Disconnected] once the stream has ended.
+    pub fn recv_next(&self) -> Result<T, PendingError> {
+        let state = self.channel.lock();
+        let mut state = self.channel.available.wait_while(state, |state| state.items.is_empty() && !state.finished)
+            .unwrap_or_else(|err| err.into_inner());
+        state.items.pop_front().ok_or(PendingError::Disconnected)
+    }
+
+    /// Blocks the current thread until the next value arrives or `timeout` has elapsed.
+    /// 
+    /// Returns [PendingError::TimedOut] if nothing arrived in time.
+    #[inline]
+    pub fn next_timeout(&self, timeout: Duration) -> Result<T, PendingError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.next_deadline(Delay::until(deadline)),
+            None => self.recv_next(),
+        }
+    }
+
+    /// Blocks the current thread until the next value arrives or `deadline` is ready.
+    /// 
+    /// Returns [PendingError::TimedOut] if nothing arrived in time.
+    pub fn next_deadline(&self, deadline: Delay) -> Result<T, PendingError> {
+        let mut state = self.channel.lock();
+        loop {
+            match state.items.pop_front() {
+                Some(item) => return Ok(item),
+                None if state.finished => return Err(PendingError::Disconnected),
+                None => (),
+            }
+            let now = Instant::now();
+            if now >= deadline.deadline() {
+                return Err(PendingError::TimedOut);
+            }
+            state = self.channel.available.wait_timeout(state, deadline.deadline() - now)
+                .unwrap_or_else(|err| err.into_inner()).0;
+        }
+    }
+}
+
+impl<T: Send + 'static> Iterator for PendingStream<T> {
+    type Item = T;
+
+    /// Blocks until the next value arrives, returning `None` once the stream has ended.
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv_next().ok()
+    }
+}
+
+impl<T: Send + 'static> Drop for PendingStream<T> {
+    fn drop(&mut self) {
+        let items = {
+            let mut state = self.channel.lock();
+            state.closed = true;
+            std::mem::take(&mut state.items)
+        };
+        // Drop the remaining items outside of the lock.
+        drop(items);
+    }
+}
+
+impl<T: Send + 'static> Drop for StreamResponder<T> {
+    fn drop(&mut self) {
+        self.channel.lock().finished = true;
+        self.channel.available.notify_all();
+    }
+}
+
+#[must_use]
+#[inline]
+pub fn stream_pair<T: Send + 'static>() -> (PendingStream<T>, StreamResponder<T>) {
+    PendingStream::pair()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_test() {
+        let stream = PendingStream::spawn(|responder| {
+            for i in 0..10u32 {
+                std::thread::sleep(Duration::from_millis(5));
+                responder.send(i).unwrap();
+            }
+        });
+        assert_eq!(stream.collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+
+        let (stream, responder) = stream_pair();
+        assert_eq!(stream.try_next(), Err(PendingError::Waiting));
+        assert_eq!(stream.next_timeout(Duration::from_millis(20)), Err(PendingError::TimedOut));
+        responder.send("progress").unwrap();
+        responder.send("done").unwrap();
+        responder.finish();
+        assert!(!stream.is_finished());
+        assert_eq!(stream.try_next(), Ok("progress"));
+        assert_eq!(stream.recv_next(), Ok("done"));
+        assert!(stream.is_finished());
+        assert_eq!(stream.try_next(), Err(PendingError::Disconnected));
+        assert_eq!(stream.recv_next(), Err(PendingError::Disconnected));
+
+        let (stream, responder) = stream_pair::<u8>();
+        drop(stream);
+        assert!(responder.is_closed());
+        assert_eq!(responder.send(1), Err(1));
+    }
+}
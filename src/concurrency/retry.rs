@@ -0,0 +1,238 @@
+use std::{
+    hash::{BuildHasher, RandomState}, time::{Duration, Instant}
+};
+
+use crate::time::Delay;
+
+use super::pending::Pending;
+
+/// How long to wait between attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// Retry immediately.
+    None,
+    /// Wait the same amount of time between every attempt.
+    Fixed(Duration),
+    /// Wait `initial`, then multiply the wait by `factor` after every attempt, up to `max`.
+    Exponential {
+        initial: Duration,
+        factor: f64,
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// The wait before the attempt after `attempt` (starting at 1), without jitter.
+    pub fn delay(self, attempt: u32) -> Duration {
+        match self {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, factor, max } => {
+                let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+                let secs = initial.as_secs_f64() * factor.powi(exponent);
+                Duration::try_from_secs_f64(secs).unwrap_or(max).min(max)
+            }
+        }
+    }
+}
+
+/// Controls how [Pending::spawn_retry] re-runs failed work.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    jitter: f64,
+    deadline: Option<Delay>,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that makes up to `max_attempts` attempts (at least one) with no waiting in between.
+    #[must_use]
+    pub const fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: if max_attempts == 0 { 1 } else { max_attempts },
+            backoff: Backoff::None,
+            jitter: 0.0,
+            deadline: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Randomly shortens each wait by up to `jitter` (from `0.0` to `1.0`) of its length,
+    /// so that many retrying workers don't all wake up at once.
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Stops retrying once `deadline` is ready, or if the next wait would pass it.
+    #[must_use]
+    pub const fn with_deadline(mut self, deadline: Delay) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    #[inline]
+    pub const fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    #[inline]
+    pub const fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    #[inline]
+    pub const fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    #[inline]
+    pub const fn deadline(&self) -> Option<Delay> {
+        self.deadline
+    }
+
+    /// The wait after `attempt` (starting at 1), or `None` if there should be no more attempts.
+    fn next_delay(&self, attempt: u32, rng: &mut Jitter) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = self.backoff.delay(attempt);
+        // `mul_f64` could panic on huge delays, which are only ever shortened here.
+        let delay = Duration::try_from_secs_f64(delay.as_secs_f64() * (1.0 - self.jitter * rng.next_f64()))
+            .map_or(delay, |jittered| jittered.min(delay));
+        match self.deadline {
+            Some(deadline) => match Instant::now().checked_add(delay) {
+                Some(next) if next < deadline.deadline() => Some(delay),
+                _ => None,
+            },
+            _ => Some(delay),
+        }
+    }
+}
+
+/// xorshift64*, good enough for spreading out retries.
+struct Jitter(u64);
+
+impl Jitter {
+    fn new() -> Self {
+        let seed = RandomState::new().hash_one((Instant::now(), std::thread::current().id()));
+        Self(seed | 1)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545F4914F6CDD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static> Pending<Result<T, Vec<E>>> {
+    /// Spawns `work`, re-running it according to `policy` for as long as it fails.
+    /// 
+    /// Resolves to the first success, or to the error from every attempt if none succeeded.
+    /// Retries stop early if the [Pending] is cancelled, including while waiting between attempts.
+    #[must_use]
+    pub fn spawn_retry<F: FnMut() -> Result<T, E> + Send + 'static>(policy: RetryPolicy, mut work: F) -> Self {
+        Pending::spawn_cancellable(move |cancelled| {
+            let mut jitter = Jitter::new();
+            let mut errors = Vec::new();
+            for attempt in 1.. {
+                match work() {
+                    Ok(value) => return Ok(value),
+                    Err(err) => errors.push(err),
+                }
+                match policy.next_delay(attempt, &mut jitter) {
+                    Some(delay) if !cancelled.wait_timeout(delay) => {}
+                    _ => break,
+                }
+            }
+            Err(errors)
+        })
+    }
+}
+
+#[must_use]
+#[inline]
+pub fn spawn_retry<T, E, F>(policy: RetryPolicy, work: F) -> Pending<Result<T, Vec<E>>>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    F: FnMut() -> Result<T, E> + Send + 'static {
+    Pending::spawn_retry(policy, work)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_test() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(10),
+            factor: 2.0,
+            max: Duration::from_millis(50),
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(10));
+        assert_eq!(backoff.delay(2), Duration::from_millis(20));
+        assert_eq!(backoff.delay(3), Duration::from_millis(40));
+        assert_eq!(backoff.delay(4), Duration::from_millis(50));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_millis(50));
+        assert_eq!(Backoff::Fixed(Duration::from_secs(1)).delay(10), Duration::from_secs(1));
+        let mut jitter = Jitter::new();
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&jitter.next_f64()));
+        }
+    }
+
+    #[test]
+    fn spawn_retry_test() {
+        let mut attempts = 0;
+        let pending = spawn_retry(RetryPolicy::new(5).with_backoff(Backoff::Fixed(Duration::from_millis(1))), move || {
+            attempts += 1;
+            if attempts < 3 { Err(attempts) } else { Ok("success") }
+        });
+        assert_eq!(pending.recv(), Ok(Ok("success")));
+
+        let policy = RetryPolicy::new(4)
+            .with_backoff(Backoff::Exponential { initial: Duration::from_millis(1), factor: 2.0, max: Duration::from_millis(4) })
+            .with_jitter(0.5);
+        let mut attempts = 0;
+        let pending = Pending::spawn_retry(policy, move || {
+            attempts += 1;
+            Err::<(), _>(attempts)
+        });
+        assert_eq!(pending.recv(), Ok(Err(vec![1, 2, 3, 4])));
+
+        let start = Instant::now();
+        let policy = RetryPolicy::new(u32::MAX)
+            .with_backoff(Backoff::Fixed(Duration::from_millis(20)))
+            .with_deadline(Delay::millis(100));
+        let pending = spawn_retry(policy, || Err::<(), _>("failed"));
+        let errors = pending.recv().unwrap().unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!((1..=5).contains(&errors.len()));
+
+        let policy = RetryPolicy::new(2)
+            .with_backoff(Backoff::Fixed(Duration::MAX))
+            .with_deadline(Delay::millis(100));
+        assert_eq!(policy.next_delay(1, &mut Jitter::new()), None);
+
+        // cancelling interrupts the wait between attempts.
+        let (attempted, attempts) = std::sync::mpsc::channel();
+        let pending = spawn_retry(RetryPolicy::new(2).with_backoff(Backoff::Fixed(Duration::from_secs(60))), move || {
+            attempted.send(()).unwrap();
+            Err::<(), _>(())
+        });
+        attempts.recv().unwrap();
+        assert!(pending.cancel());
+        assert_eq!(attempts.recv_timeout(Duration::from_secs(5)), Err(std::sync::mpsc::RecvTimeoutError::Disconnected));
+    }
+}
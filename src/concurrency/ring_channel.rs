@@ -0,0 +1,642 @@
+// Bounded lock-free ring channels for passing values between threads with low latency.
+// Every slot carries a sequence number (as in Vyukov's bounded queue) that says whether it is
+// free for the lap at a given position or holds a value for it. The producer and consumer
+// positions are kept on separate cache lines so that each side only writes to its own.
+// Blocking operations spin briefly before parking, and a side only pays for a wakeup
+// when the other side is actually parked.
+
+use std::{
+    cell::{Cell, UnsafeCell}, marker::PhantomData, mem::MaybeUninit, sync::{atomic::{fence, AtomicBool, AtomicUsize, Ordering}, Arc}, time::{Duration, Instant}
+};
+
+use crate::lowlevel::align::Align128;
+
+use super::parking;
+
+const SPIN_LIMIT: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum TrySendError<T> {
+    /// The channel is full. Also returned when a send times out.
+    #[error("Channel is full.")]
+    Full(T),
+    #[error("Channel is disconnected.")]
+    Disconnected(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Returns the value that couldn't be sent.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(value) | Self::Disconnected(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("Channel is disconnected.")]
+pub struct SendError<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum TryRecvError {
+    #[error("Channel is empty.")]
+    Empty,
+    #[error("Channel is disconnected.")]
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum RecvTimeoutError {
+    #[error("Timed out waiting for a value.")]
+    Timeout,
+    #[error("Channel is disconnected.")]
+    Disconnected,
+}
+
+/// The other side of the channel has been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("Channel is disconnected.")]
+pub struct Disconnected;
+
+struct Slot<T> {
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Shared<T> {
+    // next position to receive from. Only written by the receiver.
+    head: Align128<AtomicUsize>,
+    // next position to send to. Only written by senders.
+    tail: Align128<AtomicUsize>,
+    slots: Box<[Slot<T>]>,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+    receiver_parked: AtomicUsize,
+    senders_parked: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Spins and then parks on `address` until `ready` returns `true` or `deadline` passes.
+fn wait_for<F: FnMut() -> bool>(address: &AtomicUsize, parked: &AtomicUsize, mut ready: F, deadline: Option<Instant>) -> bool {
+    for _ in 0..SPIN_LIMIT {
+        if ready() {
+            return true;
+        }
+        std::hint::spin_loop();
+    }
+    parked.fetch_add(1, Ordering::Relaxed);
+    // pairs with the fence in `wake`, so either the waker sees `parked` or `ready` sees the change.
+    fence(Ordering::SeqCst);
+    let ready = parking::wait_until(address, ready, deadline);
+    parked.fetch_sub(1, Ordering::Relaxed);
+    ready
+}
+
+#[inline]
+fn wake(address: &AtomicUsize, parked: &AtomicUsize) {
+    fence(Ordering::SeqCst);
+    if parked.load(Ordering::Relaxed) != 0 {
+        parking::notify_all(address);
+    }
+}
+
+impl<T> Shared<T> {
+    fn new(capacity: usize, senders: usize) -> Self {
+        assert!(capacity > 0, "Ring channel capacity must be greater than zero.");
+        Self {
+            head: Align128::new(AtomicUsize::new(0)),
+            tail: Align128::new(AtomicUsize::new(0)),
+            slots: (0..capacity).map(|position| Slot {
+                sequence: AtomicUsize::new(position),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }).collect(),
+            senders: AtomicUsize::new(senders),
+            receiver_alive: AtomicBool::new(true),
+            receiver_parked: AtomicUsize::new(0),
+            senders_parked: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    fn slot(&self, position: usize) -> &Slot<T> {
+        &self.slots[position % self.slots.len()]
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(self.capacity())
+    }
+
+    #[inline]
+    fn can_push(&self) -> bool {
+        let position = self.tail.load(Ordering::Acquire);
+        self.slot(position).sequence.load(Ordering::Acquire) == position
+    }
+
+    #[inline]
+    fn can_pop(&self) -> bool {
+        let position = self.head.load(Ordering::Relaxed);
+        self.slot(position).sequence.load(Ordering::Acquire) == position.wrapping_add(1)
+    }
+
+    #[inline]
+    fn senders_disconnected(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0
+    }
+
+    #[inline]
+    fn receiver_disconnected(&self) -> bool {
+        !self.receiver_alive.load(Ordering::Acquire)
+    }
+
+    /// Claims `count` free positions starting at the tail, returning the first one.
+    /// Claims fewer (down to zero) if there isn't room for `count`.
+    fn claim(&self, count: usize, multi_producer: bool) -> (usize, usize) {
+        let mut position = self.tail.load(Ordering::Relaxed);
+        loop {
+            let free = (0..count.min(self.capacity()))
+                .take_while(|&offset| {
+                    let position = position.wrapping_add(offset);
+                    self.slot(position).sequence.load(Ordering::Acquire) == position
+                })
+                .count();
+            if free == 0 {
+                let sequence = self.slot(position).sequence.load(Ordering::Acquire);
+                // another sender already filled this position, so ours is stale.
+                if multi_producer && (sequence.wrapping_sub(position) as isize) > 0 {
+                    position = self.tail.load(Ordering::Relaxed);
+                    continue;
+                }
+                return (position, 0);
+            }
+            let next = position.wrapping_add(free);
+            if !multi_producer {
+                self.tail.store(next, Ordering::Release);
+                return (position, free);
+            }
+            match self.tail.compare_exchange_weak(position, next, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(_) => return (position, free),
+                Err(current) => position = current,
+            }
+        }
+    }
+
+    /// # Safety
+    /// `position` must have been claimed by the caller.
+    #[inline]
+    unsafe fn write(&self, position: usize, value: T) {
+        let slot = self.slot(position);
+        unsafe { (*slot.value.get()).write(value); }
+        slot.sequence.store(position.wrapping_add(1), Ordering::Release);
+    }
+
+    fn try_push(&self, value: T, multi_producer: bool) -> Result<(), TrySendError<T>> {
+        if self.receiver_disconnected() {
+            return Err(TrySendError::Disconnected(value));
+        }
+        let (position, claimed) = self.claim(1, multi_producer);
+        if claimed == 0 {
+            return Err(TrySendError::Full(value));
+        }
+        unsafe { self.write(position, value); }
+        wake(&self.tail, &self.receiver_parked);
+        Ok(())
+    }
+
+    fn try_push_batch(&self, values: &mut Vec<T>, multi_producer: bool) -> Result<usize, Disconnected> {
+        if self.receiver_disconnected() {
+            return Err(Disconnected);
+        }
+        let (position, claimed) = self.claim(values.len(), multi_producer);
+        if claimed == 0 {
+            return Ok(0);
+        }
+        for (offset, value) in values.drain(..claimed).enumerate() {
+            unsafe { self.write(position.wrapping_add(offset), value); }
+        }
+        wake(&self.tail, &self.receiver_parked);
+        Ok(claimed)
+    }
+
+    /// Must only be called by the single receiver.
+    fn pop_batch<F: FnMut(T)>(&self, max: usize, mut receive: F) -> usize {
+        let start = self.head.load(Ordering::Relaxed);
+        let mut position = start;
+        while position.wrapping_sub(start) < max {
+            let slot = self.slot(position);
+            if slot.sequence.load(Ordering::Acquire) != position.wrapping_add(1) {
+                break;
+            }
+            let value = unsafe { (*slot.value.get()).assume_init_read() };
+            slot.sequence.store(position.wrapping_add(self.capacity()), Ordering::Release);
+            position = position.wrapping_add(1);
+            receive(value);
+        }
+        let count = position.wrapping_sub(start);
+        if count != 0 {
+            self.head.store(position, Ordering::Release);
+            wake(&self.head, &self.senders_parked);
+        }
+        count
+    }
+
+    #[inline]
+    fn pop(&self) -> Option<T> {
+        let mut result = None;
+        self.pop_batch(1, |value| result = Some(value));
+        result
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// The sending side of a ring channel. [SpscSender] for a single producer, or [MpscSender],
+/// which can be cloned and shared between producers.
+pub struct RingSender<T, const MULTI_PRODUCER: bool> {
+    shared: Arc<Shared<T>>,
+    // a single producer sender can't be shared between threads.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+pub type SpscSender<T> = RingSender<T, false>;
+pub type MpscSender<T> = RingSender<T, true>;
+
+unsafe impl<T: Send> Sync for RingSender<T, true> {}
+
+impl<T> Clone for RingSender<T, true> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: self.shared.clone(),
+            _not_sync: PhantomData,
+        }
+    }
+}
+
+impl<T, const MULTI_PRODUCER: bool> std::fmt::Debug for RingSender<T, MULTI_PRODUCER> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingSender")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .field("disconnected", &self.is_disconnected())
+            .finish()
+    }
+}
+
+impl<T, const MULTI_PRODUCER: bool> RingSender<T, MULTI_PRODUCER> {
+    #[inline]
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.shared.try_push(value, MULTI_PRODUCER)
+    }
+
+    fn send_deadline(&self, mut value: T, deadline: Option<Instant>) -> Result<(), TrySendError<T>> {
+        let shared = &*self.shared;
+        loop {
+            match self.try_send(value) {
+                Err(TrySendError::Full(returned)) => value = returned,
+                result => return result,
+            }
+            let ready = || shared.can_push() || shared.receiver_disconnected();
+            if !wait_for(&shared.head, &shared.senders_parked, ready, deadline) {
+                return self.try_send(value);
+            }
+        }
+    }
+
+    /// Blocks until there is room for `value`, then sends it.
+    #[inline]
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.send_deadline(value, None).map_err(|err| SendError(err.into_inner()))
+    }
+
+    /// Blocks until there is room for `value` or `timeout` has elapsed.
+    /// Returns [TrySendError::Full] if it timed out.
+    #[inline]
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), TrySendError<T>> {
+        self.send_deadline(value, Instant::now().checked_add(timeout))
+    }
+
+    /// Sends as many values from the front of `values` as there is room for, removing them from `values`.
+    /// Returns the number of values sent.
+    #[inline]
+    pub fn try_send_batch(&self, values: &mut Vec<T>) -> Result<usize, Disconnected> {
+        self.shared.try_push_batch(values, MULTI_PRODUCER)
+    }
+
+    /// Sends every value in `values`, blocking whenever the channel is full.
+    /// If the receiver disconnects, the values that weren't sent are left in `values`.
+    pub fn send_batch(&self, values: &mut Vec<T>) -> Result<(), Disconnected> {
+        let shared = &*self.shared;
+        loop {
+            self.try_send_batch(values)?;
+            if values.is_empty() {
+                return Ok(());
+            }
+            let ready = || shared.can_push() || shared.receiver_disconnected();
+            wait_for(&shared.head, &shared.senders_parked, ready, None);
+        }
+    }
+
+    /// Returns `true` if the receiver has been dropped.
+    #[inline]
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.receiver_disconnected()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T, const MULTI_PRODUCER: bool> Drop for RingSender<T, MULTI_PRODUCER> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            parking::notify_all(&*self.shared.tail);
+        }
+    }
+}
+
+/// The receiving side of a ring channel.
+pub struct RingReceiver<T> {
+    shared: Arc<Shared<T>>,
+    // there can only be one consumer.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> std::fmt::Debug for RingReceiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingReceiver")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .field("disconnected", &self.is_disconnected())
+            .finish()
+    }
+}
+
+impl<T> RingReceiver<T> {
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        if let Some(value) = self.shared.pop() {
+            return Ok(value);
+        }
+        if !self.shared.senders_disconnected() {
+            return Err(TryRecvError::Empty);
+        }
+        // a value may have been sent right before the last sender was dropped.
+        self.shared.pop().ok_or(TryRecvError::Disconnected)
+    }
+
+    fn recv_deadline(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let shared = &*self.shared;
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => (),
+            }
+            let ready = || shared.can_pop() || shared.senders_disconnected();
+            if !wait_for(&shared.tail, &shared.receiver_parked, ready, deadline) {
+                return self.try_recv().map_err(|err| match err {
+                    TryRecvError::Empty => RecvTimeoutError::Timeout,
+                    TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                });
+            }
+        }
+    }
+
+    /// Blocks until a value is received or every sender has been dropped.
+    #[inline]
+    pub fn recv(&self) -> Result<T, Disconnected> {
+        self.recv_deadline(None).map_err(|_| Disconnected)
+    }
+
+    #[inline]
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline(Instant::now().checked_add(timeout))
+    }
+
+    /// Receives up to `max` values into `buffer` without blocking. Returns the number of values received.
+    pub fn try_recv_batch(&self, buffer: &mut Vec<T>, max: usize) -> Result<usize, Disconnected> {
+        let received = self.shared.pop_batch(max, |value| buffer.push(value));
+        if received != 0 || max == 0 || !self.shared.senders_disconnected() {
+            return Ok(received);
+        }
+        match self.shared.pop_batch(max, |value| buffer.push(value)) {
+            0 => Err(Disconnected),
+            received => Ok(received),
+        }
+    }
+
+    /// Blocks until at least one value is available, then receives up to `max` values into `buffer`.
+    pub fn recv_batch(&self, buffer: &mut Vec<T>, max: usize) -> Result<usize, Disconnected> {
+        let shared = &*self.shared;
+        loop {
+            let received = self.try_recv_batch(buffer, max)?;
+            if received != 0 || max == 0 {
+                return Ok(received);
+            }
+            let ready = || shared.can_pop() || shared.senders_disconnected();
+            wait_for(&shared.tail, &shared.receiver_parked, ready, None);
+        }
+    }
+
+    /// Returns `true` if every sender has been dropped. There may still be values left to receive.
+    #[inline]
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.senders_disconnected()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T> Drop for RingReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+        parking::notify_all(&*self.shared.head);
+    }
+}
+
+impl<T> Iterator for RingReceiver<T> {
+    type Item = T;
+
+    /// Blocks until a value is received. Ends once every sender has been dropped and the channel is empty.
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+/// Creates a bounded channel for a single producer and a single consumer that holds up to `capacity` values.
+/// 
+/// Panics if `capacity` is zero.
+pub fn spsc_channel<T>(capacity: usize) -> (SpscSender<T>, RingReceiver<T>) {
+    ring_channel(capacity)
+}
+
+/// Creates a bounded channel for many producers and a single consumer that holds up to `capacity` values.
+/// 
+/// Panics if `capacity` is zero.
+pub fn mpsc_channel<T>(capacity: usize) -> (MpscSender<T>, RingReceiver<T>) {
+    ring_channel(capacity)
+}
+
+fn ring_channel<T, const MULTI_PRODUCER: bool>(capacity: usize) -> (RingSender<T, MULTI_PRODUCER>, RingReceiver<T>) {
+    let shared = Arc::new(Shared::new(capacity, 1));
+    (
+        RingSender { shared: shared.clone(), _not_sync: PhantomData },
+        RingReceiver { shared, _not_sync: PhantomData },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spsc_test() {
+        const COUNT: usize = 100_000;
+        let (sender, receiver) = spsc_channel::<usize>(16);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Timeout));
+        let producer = std::thread::spawn(move || {
+            let mut batch = Vec::new();
+            for value in 0..COUNT {
+                if value % 3 == 0 {
+                    batch.push(value);
+                    continue;
+                }
+                batch.push(value);
+                sender.send_batch(&mut batch).unwrap();
+            }
+            sender.send_batch(&mut batch).unwrap();
+        });
+        let mut buffer = Vec::new();
+        let mut expected = 0;
+        while expected < COUNT {
+            if expected % 2 == 0 {
+                assert_eq!(receiver.recv(), Ok(expected));
+                expected += 1;
+            } else {
+                buffer.clear();
+                receiver.recv_batch(&mut buffer, 5).unwrap();
+                assert!(buffer.len() <= 5);
+                for &value in &buffer {
+                    assert_eq!(value, expected);
+                    expected += 1;
+                }
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(receiver.recv(), Err(Disconnected));
+        assert_eq!(receiver.try_recv_batch(&mut buffer, 4), Err(Disconnected));
+    }
+
+    #[test]
+    fn mpsc_test() {
+        const PRODUCERS: usize = 4;
+        const COUNT: usize = 10_000;
+        let (sender, receiver) = mpsc_channel::<(usize, usize)>(8);
+        let producers = (0..PRODUCERS).map(|producer| {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                for value in 0..COUNT {
+                    sender.send((producer, value)).unwrap();
+                }
+            })
+        }).collect::<Vec<_>>();
+        drop(sender);
+        let mut next = [0; PRODUCERS];
+        for (producer, value) in receiver {
+            // values from each producer arrive in order.
+            assert_eq!(value, next[producer]);
+            next[producer] += 1;
+        }
+        assert_eq!(next, [COUNT; PRODUCERS]);
+        producers.into_iter().for_each(|producer| producer.join().unwrap());
+    }
+
+    #[test]
+    fn full_and_disconnect_test() {
+        let (sender, receiver) = mpsc_channel(2);
+        assert_eq!(sender.try_send(0), Ok(()));
+        assert_eq!(sender.try_send(1), Ok(()));
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(sender.send_timeout(2, Duration::from_millis(10)), Err(TrySendError::Full(2)));
+        let mut batch = vec![2, 3, 4];
+        assert_eq!(sender.try_send_batch(&mut batch), Ok(0));
+        assert_eq!(receiver.try_recv(), Ok(0));
+        assert_eq!(sender.try_send_batch(&mut batch), Ok(1));
+        assert_eq!(batch, [3, 4]);
+        assert_eq!(sender.len(), 2);
+
+        let blocked = {
+            let sender = sender.clone();
+            std::thread::spawn(move || sender.send(5))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        drop(receiver);
+        assert_eq!(blocked.join().unwrap(), Err(SendError(5)));
+        assert!(sender.is_disconnected());
+        assert_eq!(sender.try_send(6), Err(TrySendError::Disconnected(6)));
+        assert_eq!(sender.send_batch(&mut batch), Err(Disconnected));
+        assert_eq!(batch, [3, 4]);
+
+        let (sender, receiver) = spsc_channel(4);
+        sender.try_send(1).unwrap();
+        drop(sender);
+        assert!(receiver.is_disconnected());
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)), Ok(1));
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)), Err(RecvTimeoutError::Disconnected));
+    }
+
+    #[test]
+    fn drop_remaining_test() {
+        let value = Arc::new(());
+        let (sender, receiver) = spsc_channel(4);
+        for _ in 0..3 {
+            sender.try_send(value.clone()).unwrap();
+        }
+        receiver.try_recv().unwrap();
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(sender);
+        drop(receiver);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
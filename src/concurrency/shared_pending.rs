@@ -0,0 +1,180 @@
+use std::{
+    future::Future, pin::Pin, sync::{Arc, Condvar, Mutex, MutexGuard}, task::{Context, Poll, Waker}, time::{Duration, Instant}
+};
+
+use crate::time::Delay;
+
+use super::pending::{Pending, PendingError};
+
+struct State<R> {
+    result: Option<Result<R, PendingError>>,
+    wakers: Vec<Waker>,
+}
+
+struct Shared<R> {
+    state: Mutex<State<R>>,
+    ready: Condvar,
+}
+
+impl<R> Shared<R> {
+    fn lock(&self) -> MutexGuard<'_, State<R>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn resolve(&self, result: Result<R, PendingError>) {
+        let wakers = {
+            let mut state = self.lock();
+            state.result = Some(result);
+            std::mem::take(&mut state.wakers)
+        };
+        self.ready.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// A [Pending] that can be cloned any number of times, where every clone can
+/// read the value without taking it away from the others.
+pub struct SharedPending<R: Clone + Send + 'static> {
+    shared: Arc<Shared<R>>,
+}
+
+impl<R: Clone + Send + 'static> Clone for SharedPending<R> {
+    fn clone(&self) -> Self {
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl<R: Clone + Send + 'static> std::fmt::Debug for SharedPending<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedPending")
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
+
+impl<R: Clone + Send + 'static> SharedPending<R> {
+    #[must_use]
+    pub fn new(pending: Pending<R>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                result: None,
+                wakers: Vec::new(),
+            }),
+            ready: Condvar::new(),
+        });
+        let resolver = shared.clone();
+        pending.on_ready(move |result| resolver.resolve(result));
+        Self { shared }
+    }
+
+    /// Returns `true` once the source [Pending] has resolved, successfully or not.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.shared.lock().result.is_some()
+    }
+
+    /// Returns a clone of the value if it has arrived.
+    pub fn try_recv(&self) -> Result<R, PendingError> {
+        self.shared.lock().result.clone().unwrap_or(Err(PendingError::Waiting))
+    }
+
+    /// Blocks the current thread until the value is ready.
+    pub fn recv(&self) -> Result<R, PendingError> {
+        let state = self.shared.lock();
+        let state = self.shared.ready.wait_while(state, |state| state.result.is_none())
+            .unwrap_or_else(|err| err.into_inner());
+        state.result.clone().unwrap_or(Err(PendingError::Waiting))
+    }
+
+    /// Blocks the current thread until the value is ready or `timeout` has elapsed.
+    /// 
+    /// Returns [PendingError::TimedOut] if the value was not ready in time.
+    #[inline]
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<R, PendingError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(Delay::until(deadline)),
+            None => self.recv(),
+        }
+    }
+
+    /// Blocks the current thread until the value is ready or `deadline` is ready.
+    /// 
+    /// Returns [PendingError::TimedOut] if the value was not ready in time.
+    pub fn recv_deadline(&self, deadline: Delay) -> Result<R, PendingError> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(result) = state.result.as_ref() {
+                return result.clone();
+            }
+            let now = Instant::now();
+            if now >= deadline.deadline() {
+                return Err(PendingError::TimedOut);
+            }
+            state = self.shared.ready.wait_timeout(state, deadline.deadline() - now)
+                .unwrap_or_else(|err| err.into_inner()).0;
+        }
+    }
+}
+
+impl<R: Clone + Send + 'static> Future for SharedPending<R> {
+    type Output = Result<R, PendingError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.lock();
+        if let Some(result) = state.result.as_ref() {
+            return Poll::Ready(result.clone());
+        }
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<R: Clone + Send + 'static> From<Pending<R>> for SharedPending<R> {
+    #[inline]
+    fn from(pending: Pending<R>) -> Self {
+        Self::new(pending)
+    }
+}
+
+impl<R: Clone + Send + 'static> Pending<R> {
+    /// Converts this [Pending] into a [SharedPending] that can be cloned.
+    #[must_use]
+    #[inline]
+    pub fn shared(self) -> SharedPending<R> {
+        SharedPending::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_pending_test() {
+        let (pending, responder) = Pending::pair();
+        let shared = pending.shared();
+        assert!(!shared.is_ready());
+        assert_eq!(shared.try_recv(), Err(PendingError::Waiting));
+        assert_eq!(shared.recv_timeout(Duration::from_millis(20)), Err(PendingError::TimedOut));
+        let waiters = (0..4).map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.recv())
+        }).collect::<Vec<_>>();
+        std::thread::sleep(Duration::from_millis(50));
+        responder.respond(String::from("shared"));
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap().as_deref(), Ok("shared"));
+        }
+        assert!(shared.is_ready());
+        assert_eq!(shared.try_recv().as_deref(), Ok("shared"));
+        assert_eq!(shared.clone().recv_deadline(Delay::millis(0)).as_deref(), Ok("shared"));
+
+        let (pending, responder) = Pending::<u32>::pair();
+        let shared = SharedPending::from(pending);
+        drop(responder);
+        assert_eq!(shared.clone().recv(), Err(PendingError::Disconnected));
+        assert_eq!(shared.try_recv(), Err(PendingError::Disconnected));
+    }
+}
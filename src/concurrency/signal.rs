@@ -0,0 +1,215 @@
+// Process signal handling for triggers.
+// The signal handler only writes the signal number to a pipe, which is async-signal-safe.
+// A watcher thread reads the pipe and runs the registered actions as ordinary code.
+// A signal's previous disposition is restored once nothing live is registered for it,
+// so that, for example, a second Ctrl-C after a shutdown trigger fires kills the process.
+
+use std::{
+    io, sync::{atomic::{AtomicI32, Ordering}, Mutex, MutexGuard}
+};
+
+pub use libc::{SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2};
+
+/// A signal number, such as [SIGINT] or [SIGTERM].
+pub type Signal = libc::c_int;
+
+type Action = Box<dyn FnOnce() + Send + 'static>;
+type Liveness = Box<dyn Fn() -> bool + Send + 'static>;
+
+struct Registration {
+    signals: Vec<Signal>,
+    action: Action,
+    // `false` once the action can no longer do anything, such as when its trigger was dropped.
+    is_alive: Liveness,
+}
+
+struct Registry {
+    watcher_started: bool,
+    // each signal with our handler installed, and the action it replaced.
+    installed: Vec<(Signal, libc::sigaction)>,
+    registrations: Vec<Registration>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    watcher_started: false,
+    installed: Vec::new(),
+    registrations: Vec::new(),
+});
+
+// The write end of the signal pipe, or -1 before the watcher is started.
+static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+fn lock_registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    let fd = WRITE_FD.load(Ordering::Relaxed);
+    if fd < 0 {
+        return;
+    }
+    let byte = signal as u8;
+    unsafe {
+        let errno = libc::__errno_location();
+        let saved = *errno;
+        // If the pipe is full, the watcher already has signals to process and this one is dropped.
+        libc::write(fd, (&raw const byte).cast(), 1);
+        *errno = saved;
+    }
+}
+
+fn start_watcher(registry: &mut Registry) -> io::Result<()> {
+    if registry.watcher_started {
+        return Ok(());
+    }
+    let mut fds = [0 as libc::c_int; 2];
+    unsafe {
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // the signal handler must never block.
+        let flags = libc::fcntl(fds[1], libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fds[1], libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            let err = io::Error::last_os_error();
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+            return Err(err);
+        }
+    }
+    let [read_fd, write_fd] = fds;
+    let spawned = std::thread::Builder::new()
+        .name("signal-watcher".to_owned())
+        .spawn(move || watch(read_fd));
+    if let Err(err) = spawned {
+        unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+        return Err(err);
+    }
+    WRITE_FD.store(write_fd, Ordering::Relaxed);
+    registry.watcher_started = true;
+    Ok(())
+}
+
+fn watch(read_fd: libc::c_int) {
+    let mut buffer = [0u8; 64];
+    loop {
+        let read = unsafe { libc::read(read_fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        if read == 0 {
+            return;
+        }
+        for &byte in &buffer[..read as usize] {
+            let signal = byte as Signal;
+            let fired: Vec<Registration> = {
+                let mut registry = lock_registry();
+                let (fired, kept) = std::mem::take(&mut registry.registrations)
+                    .into_iter()
+                    .partition(|registration| registration.signals.contains(&signal));
+                registry.registrations = kept;
+                let dead = prune(&mut registry);
+                drop(registry);
+                drop(dead);
+                fired
+            };
+            for registration in fired {
+                (registration.action)();
+            }
+        }
+    }
+}
+
+/// Installs the signal handler for `signal`, returning the action it replaced.
+fn install(signal: Signal) -> io::Result<libc::sigaction> {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(signal, &action, &mut previous) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(previous)
+    }
+}
+
+fn restore(signal: Signal, previous: &libc::sigaction) {
+    unsafe {
+        libc::sigaction(signal, previous, std::ptr::null_mut());
+    }
+}
+
+/// Removes dead registrations and restores the previous action of every signal that has
+/// no live registrations left.
+/// 
+/// Returns the removed registrations, which should be dropped after unlocking the registry.
+#[must_use]
+fn prune(registry: &mut Registry) -> Vec<Registration> {
+    let (live, dead) = std::mem::take(&mut registry.registrations)
+        .into_iter()
+        .partition(|registration| (registration.is_alive)());
+    registry.registrations = live;
+    let Registry { installed, registrations, .. } = registry;
+    installed.retain(|(signal, previous)| {
+        let registered = registrations.iter().any(|registration| registration.signals.contains(signal));
+        if !registered {
+            restore(*signal, previous);
+        }
+        registered
+    });
+    dead
+}
+
+/// Prunes the registry now, rather than on the next registration or signal.
+/// Call this once a registration's `is_alive` starts returning `false`.
+pub(crate) fn prune_now() {
+    let dead = prune(&mut lock_registry());
+    drop(dead);
+}
+
+/// Runs `action` once, on the signal watcher thread, the first time the process receives any of `signals`.
+/// The registration is dropped without running once `is_alive` returns `false`. `is_alive` is
+/// called with the registry locked, so it must not register, prune, or drop anything that does.
+/// 
+/// While a signal has registrations, it doesn't do what it did before (such as terminating the process).
+/// If any of `signals` can't be handled, nothing is registered.
+pub(crate) fn register<A, L>(signals: &[Signal], action: A, is_alive: L) -> io::Result<()>
+where
+    A: FnOnce() + Send + 'static,
+    L: Fn() -> bool + Send + 'static {
+    let mut registry = lock_registry();
+    start_watcher(&mut registry)?;
+    let mut installed = Vec::new();
+    for &signal in signals {
+        if registry.installed.iter().any(|(installed, _)| *installed == signal)
+        || installed.iter().any(|(installed, _)| *installed == signal) {
+            continue;
+        }
+        match install(signal) {
+            Ok(previous) => installed.push((signal, previous)),
+            Err(err) => {
+                for (signal, previous) in installed.iter().rev() {
+                    restore(*signal, previous);
+                }
+                return Err(err);
+            }
+        }
+    }
+    registry.installed.extend(installed);
+    registry.registrations.push(Registration {
+        signals: signals.to_vec(),
+        action: Box::new(action),
+        is_alive: Box::new(is_alive),
+    });
+    let dead = prune(&mut registry);
+    drop(registry);
+    drop(dead);
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap, hash::Hash, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, MutexGuard}, time::Duration
+};
+
+use crate::time::Delay;
+
+use super::{pending::Pending, shared_pending::SharedPending};
+
+struct Flight<V: Clone + Send + 'static> {
+    shared: SharedPending<V>,
+    // identifies the flight, since a key can be forgotten and flown again while the old work is running.
+    id: u64,
+    // `None` while the work is in flight.
+    expires: Option<Delay>,
+}
+
+type Flights<K, V> = Mutex<HashMap<K, Flight<V>>>;
+
+/// Coalesces concurrent requests for the same key so that the work is only done once.
+/// 
+/// The first caller for a key spawns the work on the rayon pool, and every caller
+/// for that key until it finishes gets the same result. With a TTL, the finished result
+/// is also handed out until the TTL runs out.
+pub struct SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static {
+    flights: Arc<Flights<K, V>>,
+    next_id: AtomicU64,
+    ttl: Option<Duration>,
+}
+
+impl<K, V> Default for SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> std::fmt::Debug for SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SingleFlight")
+            .field("keys", &self.len())
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static {
+    /// Creates a [SingleFlight] that forgets results as soon as they are finished.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            flights: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(0),
+            ttl: None,
+        }
+    }
+
+    /// Creates a [SingleFlight] that keeps finished results for `ttl`.
+    #[must_use]
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            flights: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(0),
+            ttl: Some(ttl),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Flight<V>>> {
+        self.flights.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns the result for `key`, spawning `work` on the rayon pool if there isn't already one in flight or cached.
+    pub fn get<F: FnOnce() -> V + Send + 'static>(&self, key: K, work: F) -> SharedPending<V> {
+        let mut flights = self.lock();
+        if let Some(flight) = flights.get(&key)
+        && !flight.expires.is_some_and(Delay::is_ready) {
+            return flight.shared.clone();
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let finished = (self.flights.clone(), key.clone(), self.ttl);
+        let (pending, responder) = Pending::pair();
+        let shared = pending.shared();
+        // The lock is held until the flight is inserted, so the worker can't finish before that.
+        rayon::spawn(move || {
+            // Runs after `respond`, which resolves the `SharedPending` before returning, so nobody
+            // can find the key missing while the result is still on its way.
+            scopeguard::defer! {
+                let (flights, key, ttl) = finished;
+                let mut flights = flights.lock().unwrap_or_else(|err| err.into_inner());
+                // the key may have been forgotten and given to a newer flight.
+                let Some(flight) = flights.get_mut(&key).filter(|flight| flight.id == id) else {
+                    return;
+                };
+                match ttl {
+                    Some(ttl) if !std::thread::panicking() => {
+                        flight.expires = Some(Delay::after_now(ttl));
+                    }
+                    _ => {
+                        flights.remove(&key);
+                    }
+                }
+            }
+            responder.respond(work());
+        });
+        flights.insert(key, Flight {
+            shared: shared.clone(),
+            id,
+            expires: None,
+        });
+        shared
+    }
+
+    /// Forgets the result for `key`. Callers already waiting on it still get the result.
+    #[inline]
+    pub fn forget(&self, key: &K) {
+        self.lock().remove(key);
+    }
+
+    /// Forgets every cached result that has expired.
+    pub fn purge_expired(&self) {
+        self.lock().retain(|_, flight| !flight.expires.is_some_and(Delay::is_ready));
+    }
+
+    /// The number of keys that are in flight or cached.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, sync::atomic::{AtomicUsize, Ordering}};
+
+    use super::*;
+
+    #[test]
+    fn single_flight_test() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let flights = SingleFlight::new();
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let released = Arc::new(Mutex::new(released));
+        let results = (0..8).map(|_| {
+            let calls = calls.clone();
+            let released = released.clone();
+            flights.get("key", move || {
+                calls.fetch_add(1, Ordering::Relaxed);
+                released.lock().unwrap().recv().unwrap();
+                42u32
+            })
+        }).collect::<Vec<_>>();
+        assert_eq!(flights.len(), 1);
+        release.send(()).unwrap();
+        for result in results {
+            assert_eq!(result.recv(), Ok(42));
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        // the worker removes the flight just after responding.
+        while !flights.is_empty() {
+            std::thread::yield_now();
+        }
+        assert_eq!(flights.get("key", || 7).recv(), Ok(7));
+
+        let cached = SingleFlight::with_ttl(Duration::from_millis(100));
+        assert_eq!(cached.get(1, || "first").recv(), Ok("first"));
+        assert_eq!(cached.get(1, || "second").recv(), Ok("first"));
+        std::thread::sleep(Duration::from_millis(150));
+        cached.purge_expired();
+        assert!(cached.is_empty());
+        assert_eq!(cached.get(1, || "third").recv(), Ok("third"));
+        cached.forget(&1);
+        assert_eq!(cached.get(1, || "fourth").recv(), Ok("fourth"));
+    }
+
+    #[test]
+    fn finishing_flight_test() {
+        // Wakes while the result is being delivered, after `work` has returned,
+        // and asks for the key again.
+        struct Rejoin {
+            flights: Arc<SingleFlight<&'static str, u32>>,
+            calls: Arc<AtomicUsize>,
+            rejoined: Mutex<std::sync::mpsc::Sender<SharedPending<u32>>>,
+        }
+        impl std::task::Wake for Rejoin {
+            fn wake(self: Arc<Self>) {
+                let calls = self.calls.clone();
+                let shared = self.flights.get("key", move || {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    2
+                });
+                self.rejoined.lock().unwrap().send(shared).unwrap();
+            }
+        }
+        let flights = Arc::new(SingleFlight::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let mut first = flights.get("key", move || {
+            released.recv().unwrap();
+            1
+        });
+        let (rejoined, rejoin_rx) = std::sync::mpsc::channel();
+        let waker = std::task::Waker::from(Arc::new(Rejoin {
+            flights: flights.clone(),
+            calls: calls.clone(),
+            rejoined: Mutex::new(rejoined),
+        }));
+        let mut cx = std::task::Context::from_waker(&waker);
+        assert!(std::pin::Pin::new(&mut first).poll(&mut cx).is_pending());
+        release.send(()).unwrap();
+        let rejoined = rejoin_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(rejoined.recv(), Ok(1));
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn forget_in_flight_test() {
+        fn blocked(calls: &Arc<AtomicUsize>) -> (std::sync::mpsc::Sender<()>, impl FnOnce() -> usize + Send + 'static) {
+            let (release, released) = std::sync::mpsc::channel::<()>();
+            let calls = calls.clone();
+            (release, move || {
+                let call = calls.fetch_add(1, Ordering::Relaxed) + 1;
+                released.recv().unwrap();
+                call
+            })
+        }
+        for flights in [SingleFlight::new(), SingleFlight::with_ttl(Duration::from_secs(10))] {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let (release_first, work) = blocked(&calls);
+            let first = flights.get("key", work);
+            flights.forget(&"key");
+            let (release_second, work) = blocked(&calls);
+            let second = flights.get("key", work);
+            release_first.send(()).unwrap();
+            assert_eq!(first.recv(), Ok(1));
+            // the first flight finishing must leave the second one in place.
+            let third = flights.get("key", || unreachable!());
+            release_second.send(()).unwrap();
+            assert_eq!(second.recv(), Ok(2));
+            assert_eq!(third.recv(), Ok(2));
+            assert_eq!(calls.load(Ordering::Relaxed), 2);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::VecDeque, panic::AssertUnwindSafe, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex, OnceLock}
+};
+
+use super::pending::{Pending, Responder};
+
+/// Identifies a task in a [TaskGraph].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    /// The index of the task's [Pending] in [TaskGraphRun::tasks].
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Why a task in a [TaskGraph] didn't produce a value.
+#[derive(Debug, thiserror::Error)]
+pub enum TaskError<E> {
+    #[error("Task failed.")]
+    Failed(Arc<E>),
+    #[error("Dependency {0:?} did not finish.")]
+    DependencyFailed(TaskId),
+    #[error("Task panicked.")]
+    Panicked,
+}
+
+impl<E> Clone for TaskError<E> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Failed(err) => Self::Failed(err.clone()),
+            Self::DependencyFailed(id) => Self::DependencyFailed(*id),
+            Self::Panicked => Self::Panicked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TaskGraphError {
+    #[error("Dependency cycle between tasks {0:?}.")]
+    Cycle(Vec<TaskId>),
+    #[error("Unknown task {0:?}.")]
+    UnknownTask(TaskId),
+}
+
+pub type TaskResult<T, E> = Result<Arc<T>, TaskError<E>>;
+/// The result of a whole [TaskGraph], listing every task that didn't succeed.
+pub type TaskGraphResult<E> = Result<(), Vec<(TaskId, TaskError<E>)>>;
+
+// a responder that is taken when it responds.
+type ResponderSlot<R> = Mutex<Option<Responder<R>>>;
+
+type TaskFn<T, E> = Box<dyn FnOnce(&[Arc<T>]) -> Result<T, E> + Send + 'static>;
+
+struct Node<T, E> {
+    task: TaskFn<T, E>,
+    dependencies: Vec<TaskId>,
+}
+
+/// A set of tasks with dependencies between them, run in parallel on the rayon pool.
+/// 
+/// A task runs once all of its dependencies have succeeded, and receives their outputs
+/// in the order that the dependencies were added.
+pub struct TaskGraph<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    nodes: Vec<Node<T, E>>,
+}
+
+impl<T, E> Default for TaskGraph<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> std::fmt::Debug for TaskGraph<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.nodes.iter().enumerate().map(|(index, node)| (TaskId(index), &node.dependencies)))
+            .finish()
+    }
+}
+
+/// The handles for a running [TaskGraph].
+#[derive(Debug)]
+pub struct TaskGraphRun<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    /// One [Pending] per task, indexed by [TaskId::index].
+    pub tasks: Vec<Pending<TaskResult<T, E>>>,
+    /// Resolves once every task has finished, with the error of each task that didn't succeed.
+    pub completion: Pending<TaskGraphResult<E>>,
+}
+
+impl<T, E> TaskGraph<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    /// Adds a task. Its dependencies can be added with [TaskGraph::add_dependency].
+    pub fn add_task<F: FnOnce(&[Arc<T>]) -> Result<T, E> + Send + 'static>(&mut self, task: F) -> TaskId {
+        self.nodes.push(Node {
+            task: Box::new(task),
+            dependencies: Vec::new(),
+        });
+        TaskId(self.nodes.len() - 1)
+    }
+
+    /// Adds a task that depends on `dependencies`.
+    pub fn add_task_after<F: FnOnce(&[Arc<T>]) -> Result<T, E> + Send + 'static>(&mut self, dependencies: &[TaskId], task: F) -> Result<TaskId, TaskGraphError> {
+        if let Some(&unknown) = dependencies.iter().find(|id| id.0 >= self.nodes.len()) {
+            return Err(TaskGraphError::UnknownTask(unknown));
+        }
+        let id = self.add_task(task);
+        for &dependency in dependencies {
+            self.add_dependency(id, dependency)?;
+        }
+        Ok(id)
+    }
+
+    /// Makes `task` wait for `dependency` to succeed. Adding the same dependency twice does nothing.
+    pub fn add_dependency(&mut self, task: TaskId, dependency: TaskId) -> Result<(), TaskGraphError> {
+        if dependency.0 >= self.nodes.len() {
+            return Err(TaskGraphError::UnknownTask(dependency));
+        }
+        let node = self.nodes.get_mut(task.0).ok_or(TaskGraphError::UnknownTask(task))?;
+        if !node.dependencies.contains(&dependency) {
+            node.dependencies.push(dependency);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the tasks that depend on each task.
+    fn dependents(&self) -> Vec<Vec<usize>> {
+        let mut dependents = vec![Vec::new(); self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            for dependency in node.dependencies.iter() {
+                dependents[dependency.0].push(index);
+            }
+        }
+        dependents
+    }
+
+    /// Returns an error listing the tasks that are part of (or wait on) a cycle.
+    fn check_cycles(&self, dependents: &[Vec<usize>]) -> Result<(), TaskGraphError> {
+        let mut remaining = self.nodes.iter().map(|node| node.dependencies.len()).collect::<Vec<_>>();
+        let mut ready = remaining.iter().enumerate()
+            .filter_map(|(index, &count)| (count == 0).then_some(index))
+            .collect::<VecDeque<_>>();
+        while let Some(index) = ready.pop_front() {
+            for &dependent in dependents[index].iter() {
+                remaining[dependent] -= 1;
+                if remaining[dependent] == 0 {
+                    ready.push_back(dependent);
+                }
+            }
+        }
+        let stuck = remaining.iter().enumerate()
+            .filter_map(|(index, &count)| (count > 0).then_some(TaskId(index)))
+            .collect::<Vec<_>>();
+        if stuck.is_empty() {
+            Ok(())
+        } else {
+            Err(TaskGraphError::Cycle(stuck))
+        }
+    }
+
+    /// Starts running the graph, or returns [TaskGraphError::Cycle] without running anything
+    /// if the dependencies contain a cycle.
+    pub fn run(self) -> Result<TaskGraphRun<T, E>, TaskGraphError> {
+        let dependents = self.dependents();
+        self.check_cycles(&dependents)?;
+        let (completion, completion_responder) = Pending::pair();
+        let (tasks, responders): (Vec<_>, Vec<_>) = self.nodes.iter().map(|_| {
+            let (pending, responder) = Pending::pair();
+            (pending, Mutex::new(Some(responder)))
+        }).unzip();
+        let roots = self.nodes.iter().enumerate()
+            .filter_map(|(index, node)| node.dependencies.is_empty().then_some(index))
+            .collect::<Vec<_>>();
+        let state = Arc::new(RunState {
+            remaining: self.nodes.iter().map(|node| AtomicUsize::new(node.dependencies.len())).collect(),
+            results: self.nodes.iter().map(|_| OnceLock::new()).collect(),
+            outstanding: AtomicUsize::new(self.nodes.len()),
+            dependents,
+            responders,
+            nodes: self.nodes.into_iter().map(|node| Mutex::new(Some(node))).collect(),
+            completion: Mutex::new(Some(completion_responder)),
+        });
+        if roots.is_empty() {
+            state.complete();
+        }
+        for index in roots {
+            let state = state.clone();
+            rayon::spawn(move || state.run_task(index));
+        }
+        Ok(TaskGraphRun { tasks, completion })
+    }
+}
+
+struct RunState<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    nodes: Vec<Mutex<Option<Node<T, E>>>>,
+    dependents: Vec<Vec<usize>>,
+    // dependencies that haven't finished yet.
+    remaining: Vec<AtomicUsize>,
+    results: Vec<OnceLock<TaskResult<T, E>>>,
+    responders: Vec<ResponderSlot<TaskResult<T, E>>>,
+    // tasks that haven't finished yet.
+    outstanding: AtomicUsize,
+    completion: ResponderSlot<TaskGraphResult<E>>,
+}
+
+impl<T, E> RunState<T, E>
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static {
+    fn run_task(self: Arc<Self>, index: usize) {
+        let Some(node) = self.nodes[index].lock().unwrap_or_else(|err| err.into_inner()).take() else {
+            return;
+        };
+        let mut inputs = Vec::with_capacity(node.dependencies.len());
+        let mut failed = None;
+        for &dependency in node.dependencies.iter() {
+            match self.results[dependency.0].get() {
+                Some(Ok(value)) => inputs.push(value.clone()),
+                _ => {
+                    failed = Some(dependency);
+                    break;
+                }
+            }
+        }
+        let result = match failed {
+            Some(dependency) => Err(TaskError::DependencyFailed(dependency)),
+            None => {
+                let task = node.task;
+                match std::panic::catch_unwind(AssertUnwindSafe(move || task(&inputs))) {
+                    Ok(Ok(value)) => Ok(Arc::new(value)),
+                    Ok(Err(err)) => Err(TaskError::Failed(Arc::new(err))),
+                    Err(_) => Err(TaskError::Panicked),
+                }
+            }
+        };
+        self.finish(index, result);
+    }
+
+    fn finish(self: Arc<Self>, index: usize, result: TaskResult<T, E>) {
+        let _ = self.results[index].set(result.clone());
+        if let Some(responder) = self.responders[index].lock().unwrap_or_else(|err| err.into_inner()).take() {
+            responder.respond(result);
+        }
+        for &dependent in self.dependents[index].iter() {
+            if self.remaining[dependent].fetch_sub(1, Ordering::AcqRel) == 1 {
+                let state = self.clone();
+                rayon::spawn(move || state.run_task(dependent));
+            }
+        }
+        if self.outstanding.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.complete();
+        }
+    }
+
+    fn complete(&self) {
+        let errors = self.results.iter().enumerate()
+            .filter_map(|(index, result)| match result.get() {
+                Some(Err(err)) => Some((TaskId(index), err.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if let Some(responder) = self.completion.lock().unwrap_or_else(|err| err.into_inner()).take() {
+            responder.respond(if errors.is_empty() { Ok(()) } else { Err(errors) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_graph_test() {
+        let mut graph = TaskGraph::<i32, String>::new();
+        let a = graph.add_task(|_| Ok(1));
+        let b = graph.add_task(|_| Ok(2));
+        let c = graph.add_task_after(&[a, b], |inputs| Ok(*inputs[0] * 10 + *inputs[1])).unwrap();
+        let d = graph.add_task_after(&[c], |inputs| Err(format!("rejected {}", inputs[0]))).unwrap();
+        let e = graph.add_task_after(&[d], |_| Ok(0)).unwrap();
+        let f = graph.add_task(|_| panic!("task panicked"));
+        let run = graph.run().unwrap();
+        let mut tasks = run.tasks.into_iter();
+        assert_eq!(*tasks.next().unwrap().recv().unwrap().unwrap(), 1);
+        assert_eq!(*tasks.next().unwrap().recv().unwrap().unwrap(), 2);
+        assert_eq!(*tasks.next().unwrap().recv().unwrap().unwrap(), 12);
+        assert!(matches!(tasks.next().unwrap().recv().unwrap(), Err(TaskError::Failed(err)) if *err == "rejected 12"));
+        assert!(matches!(tasks.next().unwrap().recv().unwrap(), Err(TaskError::DependencyFailed(id)) if id == d));
+        let errors = run.completion.recv().unwrap().unwrap_err();
+        let failed = errors.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(failed, [d, e, f]);
+        assert!(matches!(errors[2].1, TaskError::Panicked));
+    }
+
+    #[test]
+    fn task_graph_cycle_test() {
+        let mut graph = TaskGraph::<(), ()>::new();
+        let a = graph.add_task(|_| Ok(()));
+        let b = graph.add_task_after(&[a], |_| Ok(())).unwrap();
+        let c = graph.add_task_after(&[b], |_| Ok(())).unwrap();
+        let d = graph.add_task_after(&[c], |_| Ok(())).unwrap();
+        graph.add_dependency(b, c).unwrap();
+        assert_eq!(graph.add_dependency(a, TaskId(10)), Err(TaskGraphError::UnknownTask(TaskId(10))));
+        assert_eq!(graph.run().unwrap_err(), TaskGraphError::Cycle(vec![b, c, d]));
+
+        let run = TaskGraph::<(), ()>::new().run().unwrap();
+        assert!(run.tasks.is_empty());
+        assert!(matches!(run.completion.recv(), Ok(Ok(()))));
+    }
+}
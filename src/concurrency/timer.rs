@@ -0,0 +1,120 @@
+// A single background thread that wakes `Waker`s once their deadline passes, for things that
+// have to notice a deadline even when nothing is polling them, such as an awaited `Pending`.
+// The thread is started by the first call to `wake_at`.
+
+use std::{
+    collections::BTreeMap, sync::{Condvar, Mutex, MutexGuard}, task::Waker, time::Instant
+};
+
+/// Identifies a wakeup scheduled with [wake_at], for [cancel].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct TimerId {
+    at: Instant,
+    // breaks ties so that entries with the same deadline are woken in the order they were added.
+    id: u64,
+}
+
+struct Queue {
+    entries: BTreeMap<TimerId, Waker>,
+    next_id: u64,
+    started: bool,
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    entries: BTreeMap::new(),
+    next_id: 0,
+    started: false,
+});
+static SIGNAL: Condvar = Condvar::new();
+
+fn lock_queue() -> MutexGuard<'static, Queue> {
+    QUEUE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Wakes `waker` once `at` has passed, unless the wakeup is cancelled first.
+pub(crate) fn wake_at(at: Instant, waker: Waker) -> TimerId {
+    let mut queue = lock_queue();
+    if !queue.started {
+        std::thread::Builder::new()
+            .name("deadline-timer".to_owned())
+            .spawn(run)
+            .expect("Failed to spawn the deadline timer thread.");
+        queue.started = true;
+    }
+    let timer = TimerId { at, id: queue.next_id };
+    queue.next_id += 1;
+    let earliest = queue.entries.first_key_value().is_none_or(|(first, _)| timer < *first);
+    queue.entries.insert(timer, waker);
+    if earliest {
+        SIGNAL.notify_one();
+    }
+    timer
+}
+
+/// Cancels a wakeup, releasing its waker. Returns `false` if it already happened.
+pub(crate) fn cancel(timer: TimerId) -> bool {
+    let waker = lock_queue().entries.remove(&timer);
+    // the waker is dropped outside of the lock.
+    waker.is_some()
+}
+
+fn run() {
+    let mut queue = lock_queue();
+    loop {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        while let Some(entry) = queue.entries.first_entry() && entry.key().at <= now {
+            due.push(entry.remove());
+        }
+        if !due.is_empty() {
+            // wakers can run arbitrary code, including scheduling more wakeups.
+            drop(queue);
+            due.into_iter().for_each(Waker::wake);
+            queue = lock_queue();
+            continue;
+        }
+        queue = match queue.entries.first_key_value() {
+            Some((first, _)) => {
+                let timeout = first.at.saturating_duration_since(now);
+                SIGNAL.wait_timeout(queue, timeout).unwrap_or_else(|err| err.into_inner()).0
+            }
+            None => SIGNAL.wait(queue).unwrap_or_else(|err| err.into_inner()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, task::Wake, time::Duration};
+
+    use super::*;
+
+    struct SendWaker(std::sync::mpsc::Sender<u32>, u32);
+
+    impl Wake for SendWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.send(self.1).unwrap();
+        }
+    }
+
+    #[test]
+    fn wake_at_test() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let now = Instant::now();
+        for (delay, id) in [(60, 3), (20, 1), (40, 2)] {
+            let waker = Waker::from(Arc::new(SendWaker(sender.clone(), id)));
+            wake_at(now + Duration::from_millis(delay), waker);
+        }
+        let order = (0..3).map(|_| receiver.recv_timeout(Duration::from_secs(10)).unwrap()).collect::<Vec<_>>();
+        assert_eq!(order, [1, 2, 3]);
+        assert!(now.elapsed() >= Duration::from_millis(60));
+
+        let woken = Arc::new(SendWaker(sender, 4));
+        let timer = wake_at(Instant::now() + Duration::from_millis(20), Waker::from(woken.clone()));
+        assert!(cancel(timer));
+        assert!(!cancel(timer));
+        // cancelling releases the waker.
+        assert_eq!(Arc::strong_count(&woken), 1);
+        assert!(receiver.recv_timeout(Duration::from_millis(60)).is_err());
+    }
+}
@@ -1,55 +1,295 @@
 // A trigger is used to activate some condition from elsewhere in the program.
 
-use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex, MutexGuard, Weak}, time::{Duration, Instant}};
+
+use crate::time::Delay;
+
+use super::parking;
+
+type Callback = Box<dyn FnOnce() + Send + 'static>;
+
+/// Everything that needs to happen when a trigger is activated, besides setting the flag.
+#[derive(Default)]
+struct Hooks {
+    children: Vec<Weak<TriggerInner>>,
+    callbacks: Vec<(u64, Callback)>,
+    next_callback_id: u64,
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("children", &self.children)
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct TriggerInner {
+    trigger: AtomicBool,
+    hooks: Mutex<Hooks>,
+    // used to remove this trigger from its parent's children when it is dropped.
+    parent: Option<Weak<TriggerInner>>,
+}
+
+impl TriggerInner {
+    fn new(activated: bool, parent: Option<Weak<TriggerInner>>) -> Self {
+        Self {
+            trigger: AtomicBool::new(activated),
+            hooks: Mutex::new(Hooks::default()),
+            parent,
+        }
+    }
+}
+
+fn lock_hooks(hooks: &Mutex<Hooks>) -> MutexGuard<'_, Hooks> {
+    hooks.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl Drop for TriggerInner {
+    fn drop(&mut self) {
+        if let Some(parent) = self.parent.as_ref().and_then(Weak::upgrade) {
+            let this: *const TriggerInner = self;
+            lock_hooks(&parent.hooks).children.retain(|child| child.as_ptr() != this);
+        }
+    }
+}
+
+/// Handle for a callback registered with [Trigger::on_activate].
+/// 
+/// Dropping the handle does not unregister the callback.
+#[derive(Debug)]
+pub struct ActivationHook {
+    trigger: Weak<TriggerInner>,
+    id: u64,
+}
+
+impl ActivationHook {
+    /// Unregisters the callback. Returns `true` if the callback was removed before it ran.
+    pub fn unregister(self) -> bool {
+        let Some(trigger) = self.trigger.upgrade() else {
+            return false;
+        };
+        let callback = {
+            let mut hooks = lock_hooks(&trigger.hooks);
+            hooks.callbacks.iter()
+                .position(|(id, _)| *id == self.id)
+                .map(|index| hooks.callbacks.swap_remove(index))
+        };
+        callback.is_some()
+    }
+}
 
 /// One way activation trigger.
 #[derive(Debug, Clone)]
 pub struct Trigger {
-    trigger: Arc<AtomicBool>,
+    inner: Arc<TriggerInner>,
 }
 
 impl Trigger {
     #[inline]
     pub fn new() -> Self {
-        Self { trigger: Arc::new(AtomicBool::new(false)) }
+        Self { inner: Arc::new(TriggerInner::new(false, None)) }
+    }
+
+    /// Creates a child trigger that is activated along with this trigger.
+    /// Activating the child does not activate this trigger.
+    /// 
+    /// If this trigger is already activated, the child starts out activated.
+    pub fn child(&self) -> Trigger {
+        let mut hooks = lock_hooks(&self.inner.hooks);
+        if self.activated() {
+            return Self { inner: Arc::new(TriggerInner::new(true, None)) };
+        }
+        let child = Arc::new(TriggerInner::new(false, Some(Arc::downgrade(&self.inner))));
+        hooks.children.push(Arc::downgrade(&child));
+        Self { inner: child }
     }
 
     #[inline]
     pub fn activate(&self) -> bool {
-        !self.trigger.swap(true, Ordering::AcqRel)
+        self.trigger_ref().activate()
+    }
+
+    /// Registers `callback` to run once when the trigger is first activated, on the thread that
+    /// activates it. If the trigger is already activated, `callback` runs immediately on this thread.
+    pub fn on_activate<F: FnOnce() + Send + 'static>(&self, callback: F) -> ActivationHook {
+        let mut hooks = lock_hooks(&self.inner.hooks);
+        let id = hooks.next_callback_id;
+        hooks.next_callback_id += 1;
+        let hook = ActivationHook {
+            trigger: Arc::downgrade(&self.inner),
+            id,
+        };
+        if self.activated() {
+            drop(hooks);
+            callback();
+        } else {
+            hooks.callbacks.push((id, Box::new(callback)));
+        }
+        hook
     }
 
     #[inline]
     pub fn activated(&self) -> bool {
-        self.trigger.load(Ordering::Acquire)
+        self.inner.trigger.load(Ordering::Acquire)
+    }
+
+    /// Blocks the current thread until the trigger is activated.
+    #[inline]
+    pub fn wait(&self) {
+        self.trigger_ref().wait();
+    }
+
+    /// Blocks the current thread until the trigger is activated or `timeout` has elapsed.
+    /// Returns `true` if the trigger was activated.
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.trigger_ref().wait_timeout(timeout)
+    }
+
+    /// Blocks the current thread until the trigger is activated or `deadline` is ready.
+    /// Returns `true` if the trigger was activated.
+    #[inline]
+    pub fn wait_until(&self, deadline: Delay) -> bool {
+        self.trigger_ref().wait_until(deadline)
     }
 
     #[inline]
     pub fn trigger_ref(&self) -> TriggerRef<'_> {
-        TriggerRef::new(&self.trigger)
+        TriggerRef {
+            trigger_ref: &self.inner.trigger,
+            hooks: Some(&self.inner.hooks),
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct TriggerRef<'a> {
     trigger_ref: &'a AtomicBool,
+    // `None` when made from a plain `AtomicBool`.
+    hooks: Option<&'a Mutex<Hooks>>,
 }
 
 impl<'a> TriggerRef<'a> {
     #[inline]
     pub fn new(trigger_ref: &'a AtomicBool) -> Self {
-        Self { trigger_ref }
+        Self { trigger_ref, hooks: None }
     }
 
     #[inline]
     pub fn activate(self) -> bool {
-        !self.trigger_ref.swap(true, Ordering::AcqRel)
+        let activated = !self.trigger_ref.swap(true, Ordering::AcqRel);
+        if activated {
+            parking::notify_all(self.trigger_ref);
+            if let Some(hooks) = self.hooks {
+                // Hooks added from here on see that this trigger is activated, so the lists are final.
+                let (children, callbacks) = {
+                    let mut hooks = lock_hooks(hooks);
+                    (std::mem::take(&mut hooks.children), std::mem::take(&mut hooks.callbacks))
+                };
+                for child in children.iter().filter_map(Weak::upgrade) {
+                    Trigger { inner: child }.activate();
+                }
+                for (_, callback) in callbacks {
+                    callback();
+                }
+            }
+        }
+        activated
     }
 
     #[inline]
     pub fn activated(self) -> bool {
         self.trigger_ref.load(Ordering::Acquire)
     }
+
+    /// Blocks the current thread until the trigger is activated.
+    #[inline]
+    pub fn wait(self) {
+        parking::wait_until(self.trigger_ref, || self.activated(), None);
+    }
+
+    /// Blocks the current thread until the trigger is activated or `timeout` has elapsed.
+    /// Returns `true` if the trigger was activated.
+    #[inline]
+    pub fn wait_timeout(self, timeout: Duration) -> bool {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => parking::wait_until(self.trigger_ref, || self.activated(), Some(deadline)),
+            None => {
+                self.wait();
+                true
+            }
+        }
+    }
+
+    /// Blocks the current thread until the trigger is activated or `deadline` is ready.
+    /// Returns `true` if the trigger was activated.
+    #[inline]
+    pub fn wait_until(self, deadline: Delay) -> bool {
+        parking::wait_until(self.trigger_ref, || self.activated(), Some(deadline.deadline()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Trigger {
+    /// Creates a trigger that is activated when the process receives any of `signals`.
+    /// 
+    /// Until the trigger is activated or dropped, the signals no longer do what they did before,
+    /// such as terminating the process. After that, their previous behavior is restored.
+    /// 
+    /// ```rust,no_run
+    /// use dmf::concurrency::{signal::{SIGINT, SIGTERM}, trigger::Trigger};
+    /// let shutdown = Trigger::on_signals(&[SIGINT, SIGTERM]).unwrap();
+    /// while !shutdown.activated() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn on_signals(signals: &[super::signal::Signal]) -> std::io::Result<Self> {
+        let trigger = Self::new();
+        trigger.activate_on_signals(signals)?;
+        Ok(trigger)
+    }
+
+    /// Activates this trigger when the process receives any of `signals`.
+    /// 
+    /// Once this trigger is activated or dropped, the signals' previous behavior is restored.
+    pub fn activate_on_signals(&self, signals: &[super::signal::Signal]) -> std::io::Result<()> {
+        let trigger = Arc::downgrade(&self.inner);
+        let alive = Arc::new(AtomicBool::new(true));
+        let watched = alive.clone();
+        super::signal::register(signals, move || {
+            if let Some(inner) = trigger.upgrade() {
+                Trigger { inner }.activate();
+            }
+        }, move || watched.load(Ordering::Acquire))?;
+        // Runs when the callback runs on activation, or is dropped along with this trigger.
+        let registration = SignalRegistration { alive };
+        self.on_activate(move || drop(registration));
+        Ok(())
+    }
+}
+
+/// Unregisters a trigger's signal handling when dropped.
+#[cfg(target_os = "linux")]
+struct SignalRegistration {
+    alive: Arc<AtomicBool>,
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for SignalRegistration {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Release);
+        super::signal::prune_now();
+    }
+}
+
+/// Creates a trigger that is activated on `SIGINT` or `SIGTERM`, for graceful shutdown.
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn signal_trigger() -> std::io::Result<Trigger> {
+    Trigger::on_signals(&[super::signal::SIGINT, super::signal::SIGTERM])
 }
 
 #[cfg(test)]
@@ -83,4 +323,134 @@ mod tests {
         assert!(trig2.activated());
         assert!(trig2_ref.activated());
     }
+
+    #[test]
+    fn on_activate_test() {
+        use std::sync::atomic::AtomicUsize;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let trigger = Trigger::new();
+        let hooks = (0..4).map(|_| {
+            let calls = calls.clone();
+            trigger.on_activate(move || {
+                calls.fetch_add(1, Ordering::Relaxed);
+            })
+        }).collect::<Vec<_>>();
+        let mut hooks = hooks.into_iter();
+        assert!(hooks.next().unwrap().unregister());
+        let child = trigger.child();
+        let child_calls = calls.clone();
+        child.on_activate(move || {
+            child_calls.fetch_add(10, Ordering::Relaxed);
+        });
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+        let activators = (0..4).map(|_| {
+            let trigger = trigger.clone();
+            std::thread::spawn(move || trigger.activate())
+        }).collect::<Vec<_>>();
+        let first = activators.into_iter().map(|activator| activator.join().unwrap()).filter(|first| *first).count();
+        assert_eq!(first, 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 13);
+        assert!(!hooks.next().unwrap().unregister());
+
+        let late_calls = calls.clone();
+        let late = trigger.on_activate(move || {
+            late_calls.fetch_add(100, Ordering::Relaxed);
+        });
+        assert_eq!(calls.load(Ordering::Relaxed), 113);
+        assert!(!late.unregister());
+    }
+
+    #[test]
+    fn child_trigger_test() {
+        let server = Trigger::new();
+        let connection = server.child();
+        let requests = (0..3).map(|_| connection.child()).collect::<Vec<_>>();
+        assert!(requests[0].activate());
+        assert!(!connection.activated());
+        assert!(!server.activated());
+
+        let waiter = {
+            let request = requests[1].clone();
+            std::thread::spawn(move || request.wait_timeout(Duration::from_secs(10)))
+        };
+        assert!(server.activate());
+        assert!(connection.activated());
+        assert!(requests.iter().all(Trigger::activated));
+        assert!(waiter.join().unwrap());
+        assert!(server.child().activated());
+
+        // dropped children are removed from the parent.
+        let parent = Trigger::new();
+        let children = (0..100).map(|_| parent.child()).collect::<Vec<_>>();
+        assert_eq!(lock_hooks(&parent.inner.hooks).children.len(), 100);
+        let kept = children[7].clone();
+        drop(children);
+        assert_eq!(lock_hooks(&parent.inner.hooks).children.len(), 1);
+        parent.activate();
+        assert!(kept.activated());
+        assert!(lock_hooks(&parent.inner.hooks).children.is_empty());
+    }
+
+    #[test]
+    fn trigger_wait_test() {
+        let trigger = Trigger::new();
+        assert!(!trigger.wait_timeout(Duration::from_millis(20)));
+        assert!(!trigger.trigger_ref().wait_until(Delay::millis(20)));
+        let waiters = (0..4).map(|_| {
+            let trigger = trigger.clone();
+            std::thread::spawn(move || {
+                trigger.wait();
+                trigger.trigger_ref().wait_timeout(Duration::from_secs(10))
+            })
+        }).collect::<Vec<_>>();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(trigger.activate());
+        for waiter in waiters {
+            assert!(waiter.join().unwrap());
+        }
+        assert!(trigger.wait_until(Delay::millis(0)));
+
+        let flag = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            let waiter = s.spawn(|| TriggerRef::new(&flag).wait_timeout(Duration::from_secs(10)));
+            std::thread::sleep(Duration::from_millis(20));
+            TriggerRef::new(&flag).activate();
+            assert!(waiter.join().unwrap());
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn signal_trigger_test() {
+        use crate::concurrency::signal::{SIGUSR1, SIGUSR2};
+        fn handler(signal: i32) -> libc::sighandler_t {
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                libc::sigaction(signal, std::ptr::null(), &mut action);
+                action.sa_sigaction
+            }
+        }
+        // a failed registration leaves nothing installed.
+        assert!(Trigger::on_signals(&[SIGUSR1, libc::SIGKILL]).is_err());
+        assert_eq!(handler(SIGUSR1), libc::SIG_DFL);
+
+        let dropped = Trigger::on_signals(&[SIGUSR1]).unwrap();
+        assert_ne!(handler(SIGUSR1), libc::SIG_DFL);
+        drop(dropped);
+        assert_eq!(handler(SIGUSR1), libc::SIG_DFL);
+        let activated = Trigger::on_signals(&[SIGUSR1]).unwrap();
+        assert_ne!(handler(SIGUSR1), libc::SIG_DFL);
+        activated.activate();
+        assert_eq!(handler(SIGUSR1), libc::SIG_DFL);
+
+        let trigger = Trigger::on_signals(&[SIGUSR2]).unwrap();
+        let child = trigger.child();
+        assert!(!trigger.activated());
+        unsafe { libc::raise(SIGUSR2); }
+        assert!(trigger.wait_timeout(Duration::from_secs(10)));
+        assert!(child.wait_timeout(Duration::from_secs(10)));
+        // nothing is registered for it anymore.
+        assert_eq!(handler(SIGUSR2), libc::SIG_DFL);
+    }
 }
\ No newline at end of file
@@ -7,6 +7,34 @@ macro_rules! align_structs {
                 #[repr(C, align($num))]
                 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
                 pub struct [<Align $num>]<T>(T);
+
+                impl<T> [<Align $num>]<T> {
+                    #[inline(always)]
+                    pub const fn new(value: T) -> Self {
+                        Self(value)
+                    }
+
+                    #[inline(always)]
+                    pub fn into_inner(self) -> T {
+                        self.0
+                    }
+                }
+
+                impl<T> std::ops::Deref for [<Align $num>]<T> {
+                    type Target = T;
+
+                    #[inline(always)]
+                    fn deref(&self) -> &T {
+                        &self.0
+                    }
+                }
+
+                impl<T> std::ops::DerefMut for [<Align $num>]<T> {
+                    #[inline(always)]
+                    fn deref_mut(&mut self) -> &mut T {
+                        &mut self.0
+                    }
+                }
             }
         )*
     };
@@ -0,0 +1,289 @@
+use std::sync::atomic::{self, Ordering};
+
+/// Integer types that have an atomic counterpart usable by [AtomicCounter].
+pub trait AtomicInteger: Copy + crate::Sealed<AtomicCounter<Self>> {
+    type Atomic: std::fmt::Debug + Default + Send + Sync;
+}
+
+/// What an [AtomicCounter] does when incrementing or decrementing would overflow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// Wrap around at the bounds of the type.
+    #[default]
+    Wrapping,
+    /// Stay at the bounds of the type.
+    Saturating,
+    /// Panic, leaving the counter unchanged.
+    Panicking,
+}
+
+/// A thread-safe [Counter](super::counter::Counter).
+#[derive(Debug)]
+pub struct AtomicCounter<T: AtomicInteger> {
+    value: T::Atomic,
+    ordering: Ordering,
+    overflow: Overflow,
+}
+
+/// The strongest ordering that can be used for a load, given the ordering used for writes.
+#[inline(always)]
+const fn load_ordering(ordering: Ordering) -> Ordering {
+    match ordering {
+        Ordering::Release | Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::AcqRel | Ordering::Acquire => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    }
+}
+
+/// The strongest ordering that can be used for a store, given the ordering used for writes.
+#[inline(always)]
+const fn store_ordering(ordering: Ordering) -> Ordering {
+    match ordering {
+        Ordering::Acquire | Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::AcqRel | Ordering::Release => Ordering::Release,
+        _ => Ordering::SeqCst,
+    }
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn overflow_panic(operation: &str) -> ! {
+    panic!("AtomicCounter overflowed on {operation}.")
+}
+
+impl<T: AtomicInteger> AtomicCounter<T> {
+    #[inline]
+    pub const fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+
+    #[inline]
+    pub const fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Sets the memory ordering used by every operation on the counter. The default is [Ordering::SeqCst].
+    #[inline]
+    pub const fn with_ordering(mut self, ordering: Ordering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Sets the overflow policy. The default is [Overflow::Wrapping].
+    #[inline]
+    pub const fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+// Each impl is only compiled where the target has atomics of the type's width.
+// `AtomicU128`/`AtomicI128` are unstable, so 128-bit integers are always left out.
+macro_rules! atomic_counter_impls {
+    (u128) => {};
+    (i128) => {};
+    (u8) => { atomic_counter_impls!(@impl u8, "8"); };
+    (i8) => { atomic_counter_impls!(@impl i8, "8"); };
+    (u16) => { atomic_counter_impls!(@impl u16, "16"); };
+    (i16) => { atomic_counter_impls!(@impl i16, "16"); };
+    (u32) => { atomic_counter_impls!(@impl u32, "32"); };
+    (i32) => { atomic_counter_impls!(@impl i32, "32"); };
+    (u64) => { atomic_counter_impls!(@impl u64, "64"); };
+    (i64) => { atomic_counter_impls!(@impl i64, "64"); };
+    (usize) => { atomic_counter_impls!(@impl usize, "ptr"); };
+    (isize) => { atomic_counter_impls!(@impl isize, "ptr"); };
+    (@impl $type:ident, $bits:literal) => {
+        paste::paste!{
+            #[cfg(target_has_atomic = $bits)]
+            impl crate::Sealed<AtomicCounter<$type>> for $type {}
+            #[cfg(target_has_atomic = $bits)]
+            impl AtomicInteger for $type {
+                type Atomic = atomic::[<Atomic $type:camel>];
+            }
+
+            #[cfg(target_has_atomic = $bits)]
+            impl AtomicCounter<$type> {
+                #[inline]
+                pub const fn new(init: $type) -> Self {
+                    Self {
+                        value: atomic::[<Atomic $type:camel>]::new(init),
+                        ordering: Ordering::SeqCst,
+                        overflow: Overflow::Wrapping,
+                    }
+                }
+
+                #[inline]
+                pub fn load(&self) -> $type {
+                    self.value.load(load_ordering(self.ordering))
+                }
+
+                #[inline]
+                pub fn store(&self, value: $type) {
+                    self.value.store(value, store_ordering(self.ordering));
+                }
+
+                #[inline]
+                pub fn swap(&self, value: $type) -> $type {
+                    self.value.swap(value, self.ordering)
+                }
+
+                #[inline]
+                pub fn into_inner(self) -> $type {
+                    self.value.into_inner()
+                }
+
+                /// Adds `delta` according to the overflow policy and returns the previous value.
+                #[track_caller]
+                fn fetch_step(&self, delta: $type, add: bool) -> $type {
+                    match self.overflow {
+                        Overflow::Wrapping if add => self.value.fetch_add(delta, self.ordering),
+                        Overflow::Wrapping => self.value.fetch_sub(delta, self.ordering),
+                        overflow => {
+                            let step = |value: $type| if add {
+                                value.checked_add(delta)
+                            } else {
+                                value.checked_sub(delta)
+                            };
+                            match self.value.fetch_update(self.ordering, load_ordering(self.ordering), step) {
+                                Ok(previous) => previous,
+                                Err(_) if overflow == Overflow::Panicking => {
+                                    overflow_panic(if add { "increment" } else { "decrement" })
+                                }
+                                Err(saturated) => saturated,
+                            }
+                        }
+                    }
+                }
+
+                #[inline]
+                fn step_result(&self, previous: $type, add: bool) -> $type {
+                    match self.overflow {
+                        Overflow::Wrapping if add => previous.wrapping_add(1),
+                        Overflow::Wrapping => previous.wrapping_sub(1),
+                        _ if add => previous.saturating_add(1),
+                        _ => previous.saturating_sub(1),
+                    }
+                }
+
+                #[doc = "Increments the value."]
+                #[inline]
+                #[track_caller]
+                pub fn increment(&self) {
+                    self.fetch_step(1, true);
+                }
+
+                #[doc = "Pre-increments value before returning it."]
+                #[inline]
+                #[track_caller]
+                pub fn pre_increment(&self) -> $type {
+                    self.step_result(self.fetch_step(1, true), true)
+                }
+
+                #[doc = "Increments and returns the value prior to incrementation."]
+                #[inline]
+                #[track_caller]
+                pub fn post_increment(&self) -> $type {
+                    self.fetch_step(1, true)
+                }
+
+                #[doc = "Decrements the value."]
+                #[inline]
+                #[track_caller]
+                pub fn decrement(&self) {
+                    self.fetch_step(1, false);
+                }
+
+                #[doc = "Pre-decrements value before returning it."]
+                #[inline]
+                #[track_caller]
+                pub fn pre_decrement(&self) -> $type {
+                    self.step_result(self.fetch_step(1, false), false)
+                }
+
+                #[doc = "Decrements and returns the value prior to decrementation."]
+                #[inline]
+                #[track_caller]
+                pub fn post_decrement(&self) -> $type {
+                    self.fetch_step(1, false)
+                }
+            }
+
+            #[cfg(target_has_atomic = $bits)]
+            impl Default for AtomicCounter<$type> {
+                #[inline]
+                fn default() -> Self {
+                    Self::new(0)
+                }
+            }
+
+            #[cfg(target_has_atomic = $bits)]
+            impl From<$type> for AtomicCounter<$type> {
+                #[inline]
+                fn from(value: $type) -> Self {
+                    Self::new(value)
+                }
+            }
+
+            #[cfg(target_has_atomic = $bits)]
+            impl From<AtomicCounter<$type>> for $type {
+                #[inline]
+                fn from(value: AtomicCounter<$type>) -> Self {
+                    value.into_inner()
+                }
+            }
+        }
+    };
+}
+
+crate::for_each_int_type!(atomic_counter_impls);
+
+#[inline]
+pub fn atomic_counter<T: AtomicInteger>(initial: T) -> AtomicCounter<T>
+where AtomicCounter<T>: From<T> {
+    AtomicCounter::from(initial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_counter_tests() {
+        let count = atomic_counter(0u32);
+        assert_eq!(count.post_increment(), 0);
+        assert_eq!(count.pre_increment(), 2);
+        assert_eq!(count.post_decrement(), 2);
+        assert_eq!(count.pre_decrement(), 0);
+        assert_eq!(count.pre_decrement(), u32::MAX);
+        count.increment();
+        assert_eq!(count.load(), 0);
+
+        let count = AtomicCounter::<i8>::new(i8::MAX - 1).with_overflow(Overflow::Saturating);
+        assert_eq!(count.pre_increment(), i8::MAX);
+        assert_eq!(count.post_increment(), i8::MAX);
+        assert_eq!(count.pre_increment(), i8::MAX);
+        count.store(i8::MIN);
+        assert_eq!(count.pre_decrement(), i8::MIN);
+
+        let count = AtomicCounter::<usize>::new(usize::MAX)
+            .with_overflow(Overflow::Panicking)
+            .with_ordering(Ordering::AcqRel);
+        assert_eq!(count.ordering(), Ordering::AcqRel);
+        assert!(std::panic::catch_unwind(|| count.increment()).is_err());
+        assert_eq!(count.load(), usize::MAX);
+        assert_eq!(count.pre_decrement(), usize::MAX - 1);
+
+        let count = AtomicCounter::<u64>::default().with_ordering(Ordering::Relaxed);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        count.increment();
+                    }
+                });
+            }
+        });
+        assert_eq!(count.into_inner(), 4000);
+    }
+}
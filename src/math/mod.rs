@@ -1,2 +1,3 @@
+pub mod atomic_counter;
 pub mod counter;
 pub mod stride;
\ No newline at end of file
@@ -17,6 +17,25 @@ impl<R> TimedResult<R> {
     }
 }
 
+/// Timing of work that was queued before it ran, such as [crate::concurrency::pending::Pending::spawn_timed].
+#[derive(Debug)]
+pub struct TimedTaskResult<R> {
+    pub result: R,
+    /// Time spent waiting to start.
+    pub queued: Duration,
+    /// Time spent running.
+    pub elapsed: Duration,
+}
+
+impl<R> TimedTaskResult<R> {
+    /// Time from being queued until finishing.
+    #[must_use]
+    #[inline]
+    pub fn total(&self) -> Duration {
+        self.queued + self.elapsed
+    }
+}
+
 #[must_use]
 #[inline(always)]
 pub fn time_it<R, F: FnOnce() -> R>(f: F) -> TimedResult<R> {